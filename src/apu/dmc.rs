@@ -8,12 +8,11 @@ pub struct DMC {
     irq_enabled: bool,
     pub interrupt: bool,
     loop_flag: bool,
-    pub cpu_stall: bool,
+    pub dma_request: bool, // the memory reader wants a byte. The CPU's DMA unit fetches it and passes it to load_sample().
     rate_index: usize,
     cpu_cycles_left: u16,
 
     // Memory reader
-    sample_byte: u8, // passed in every APU clock cycle, need to think of a better way to read CPU from APU
     sample_buffer: Option<u8>, // buffer that the output unit draws into its shift register, wrapped in Option to denote 'emptiness'
     pub sample_address: usize, // start of sample in memory
     pub sample_length: usize, // number of bytes starting from sample_address that constitute the sample. each byte has 8 bits that can raise or lower the output level, at a speed determined by rate_index
@@ -33,10 +32,9 @@ impl DMC {
             irq_enabled: false,
             interrupt: false,
             loop_flag: false,
-            cpu_stall: false,
+            dma_request: false,
             rate_index: 0,
             cpu_cycles_left: 0,
-            sample_byte: 0,
             sample_buffer: None,
             sample_address: 0,
             sample_length: 0,
@@ -47,14 +45,14 @@ impl DMC {
         }
     }
 
-    pub fn clock(&mut self, sample_byte: u8) {
+    pub fn clock(&mut self) {
         if self.enabled {
-            self.clock_memory_reader(sample_byte);
+            self.clock_memory_reader();
             self.clock_output_unit();
         }
     }
 
-    fn clock_memory_reader(&mut self, sample_byte: u8) {
+    fn clock_memory_reader(&mut self) {
         // When a sample is (re)started, the current address is set to the sample address, and bytes remaining is set to the sample length.
         if self.bytes_remaining == 0 && self.loop_flag {
             self.current_address = self.sample_address;
@@ -65,23 +63,31 @@ impl DMC {
         if self.sample_buffer.is_none() && self.bytes_remaining != 0 {
             // The CPU is stalled for up to 4 CPU cycles to allow the longest possible write (the return address and write after an IRQ) to finish.
             // If OAM DMA is in progress, it is paused for two cycles. The sample fetch always occurs on an even CPU cycle due to its alignment with the APU.
-            self.cpu_stall = true;
-            // The sample buffer is filled with the next sample byte read from the current address, subject to whatever mapping hardware is present.
-            self.sample_buffer = Some(sample_byte);
-            // The address is incremented; if it exceeds $FFFF, it is wrapped around to $8000.
-            if self.current_address == 0xFFFF {
-                self.current_address = 0x8000
-            } else {
-                self.current_address += 1;
-            }
-            // The bytes remaining counter is decremented; if it becomes zero and the loop flag is set, the sample is restarted (see above);
-            // otherwise, if the bytes remaining counter becomes zero and the IRQ enabled flag is set, the interrupt flag is set.
-            self.bytes_remaining -= 1;
+            self.dma_request = true;
         } else if self.sample_buffer.is_none() && self.irq_enabled {
             self.interrupt = true;
         }
     }
 
+    // The DMA unit has fetched the byte at current_address.
+    pub fn load_sample(&mut self, sample_byte: u8) {
+        self.dma_request = false;
+        if self.bytes_remaining == 0 {
+            return; // the channel was disabled while the DMA was underway
+        }
+        // The sample buffer is filled with the next sample byte read from the current address, subject to whatever mapping hardware is present.
+        self.sample_buffer = Some(sample_byte);
+        // The address is incremented; if it exceeds $FFFF, it is wrapped around to $8000.
+        if self.current_address == 0xFFFF {
+            self.current_address = 0x8000
+        } else {
+            self.current_address += 1;
+        }
+        // The bytes remaining counter is decremented; if it becomes zero and the loop flag is set, the sample is restarted (see above);
+        // otherwise, if the bytes remaining counter becomes zero and the IRQ enabled flag is set, the interrupt flag is set.
+        self.bytes_remaining -= 1;
+    }
+
     fn clock_output_unit(&mut self) {
         // When the timer outputs a clock, the following actions occur in order:
         // If the silence flag is clear, the output level changes based on bit 0 of the shift register.
@@ -1,8 +1,8 @@
 mod noise;
-mod square;
+pub mod square;
 mod triangle;
 mod dmc;
-mod envelope;
+pub mod envelope;
 pub mod serialize;
 
 use noise::Noise;
@@ -36,7 +36,6 @@ pub struct Apu {
     interrupt_inhibit: bool,
     frame_interrupt: bool,
     cycle: usize,
-    pub trigger_irq: bool,
 }
 
 impl Apu {
@@ -50,26 +49,39 @@ impl Apu {
             noise:       Noise::new(),
             dmc:           DMC::new(),
 
-            square_table: square_table,
-            tnd_table: tnd_table,
+            square_table,
+            tnd_table,
 
-            frame_sequence: 0,
+            frame_sequence: 4, // the 4-step sequence is selected at power-on
             frame_counter: 0,
             interrupt_inhibit: false,
             frame_interrupt: false,
             cycle: 0,
-            trigger_irq: false,
         }
     }
 
-    pub fn clock(&mut self, sample_byte: u8) -> f32 {
+    // The reset button. From https://wiki.nesdev.com/w/index.php/CPU_power_up_state:
+    // "APU mode in $4017 was unchanged ... APU was silenced ($4015 = 0) ... APU triangle phase is reset to 0 ...
+    // APU DPCM output ANDed with 1 (upper 6 bits cleared)", and the frame counter starts over as if $4017 had been
+    // written with its last value.
+    pub fn reset(&mut self) {
+        self.write_control(0);
+        self.triangle.reset();
+        self.dmc.sample &= 1;
+        let frame_counter_value = (if self.frame_sequence == 5 {0x80} else {0}) | (if self.interrupt_inhibit {0x40} else {0});
+        self.write_frame_counter(frame_counter_value);
+        self.frame_counter = 0;
+        self.cycle = 0;
+    }
+
+    pub fn clock(&mut self) -> f32 {
         // Clock each channel
         self.square1.clock();
         self.square2.clock();
         self.triangle.clock();
         self.triangle.clock(); // hacky. clocking triangle twice because it runs every CPU cycle
         self.noise.clock();
-        self.dmc.clock(sample_byte);
+        self.dmc.clock();
 
         // Step frame counter if necessary
         if FRAME_COUNTER_STEPS.contains(&self.cycle) {
@@ -146,7 +158,7 @@ impl Apu {
             self.noise.clock_length_counter();
         }
         if self.frame_sequence == 4 && self.frame_counter == 3 && !self.interrupt_inhibit {
-            self.trigger_irq = true;
+            self.frame_interrupt = true;
         }
         // advance counter
         self.frame_counter = self.frame_counter.wrapping_add(1);
@@ -199,6 +211,11 @@ impl Apu {
         }
     }
 
+    // The frame counter and the DMC both hold the CPU's IRQ line low for as long as their interrupt flags are set.
+    pub fn irq_asserted(&self) -> bool {
+        self.frame_interrupt || self.dmc.interrupt
+    }
+
     // CPU reads from $4015
     pub fn read_status(&mut self) -> u8 {
         // IF-D NT21: 	DMC interrupt (I), frame interrupt (F), DMC active (D), length counter > 0 (N/T/2/1)
@@ -236,9 +253,10 @@ impl Apu {
     fn write_frame_counter(&mut self, value: u8) {
         // 0 selects 4-step sequence, 1 selects 5-step sequence
         self.frame_sequence = if value & (1<<7) == 0 { 4 } else { 5 };
-        // If set, the frame interrupt flag is cleared, otherwise it is unaffected.
-        if value & (1<<6) != 0 {
-            self.interrupt_inhibit = false;
+        // Interrupt inhibit flag. If set, the frame interrupt flag is cleared, otherwise it is unaffected.
+        self.interrupt_inhibit = value & (1<<6) != 0;
+        if self.interrupt_inhibit {
+            self.frame_interrupt = false;
         }
         // If the mode flag is set, then both "quarter frame" and "half frame" signals are also generated.
         if self.frame_sequence == 5 {
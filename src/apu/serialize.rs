@@ -18,6 +18,5 @@ impl super::Apu{
         self.interrupt_inhibit = data.interrupt_inhibit;
         self.frame_interrupt = data.frame_interrupt;
         self.cycle = data.cycle;
-        self.trigger_irq = data.trigger_irq;
     }
 }
@@ -13,6 +13,7 @@ pub struct Square {
     pub enabled: bool,
     constant_volume_flag: bool, // (0: use volume from envelope; 1: use constant volume)
     first_channel: bool, // hack to detect timing difference in clock_sweep()
+    no_sweep: bool, // for the MMC5's pulses, which have no sweep unit to silence them
 
     timer: u16,
     timer_period: u16,
@@ -37,7 +38,8 @@ impl Square {
             sample: 0,
             enabled: false,
             constant_volume_flag: false,
-            first_channel: first_channel,
+            first_channel,
+            no_sweep: false,
             timer: 0,
             timer_period: 0,
             duty_cycle: DUTY_CYCLE_SEQUENCES[0],
@@ -54,6 +56,14 @@ impl Square {
         }
     }
 
+    // A pulse channel like the APU's but without the sweep unit, and so without the muting that goes with it.
+    pub fn without_sweep() -> Self {
+        Square {
+            no_sweep: true,
+            ..Square::new(false)
+        }
+    }
+
     pub fn clock(&mut self) {
         // The sequencer is clocked by an 11-bit timer. Given the timer value t = HHHLLLLLLLL formed by timer high and timer low, this timer is updated every APU cycle
         // (i.e., every second CPU cycle), and counts t, t-1, ..., 0, t, t-1, ..., clocking the waveform generator when it goes from 0 to t.
@@ -66,9 +76,9 @@ impl Square {
         // Update volume for this channel
         // The mixer receives the current envelope volume except when
         self.sample = if self.duty_cycle[self.duty_counter] == 0 // the sequencer output is zero, or
-            || self.timer_period > 0x7FF // overflow from the sweep unit's adder is silencing the channel,
+            || (self.timer_period > 0x7FF && !self.no_sweep) // overflow from the sweep unit's adder is silencing the channel,
             || self.length_counter == 0 // the length counter is zero, or
-            || self.timer_period < 8 // the timer has a value less than eight.
+            || (self.timer_period < 8 && !self.no_sweep) // the timer has a value less than eight.
             {
                 0
             } else if self.constant_volume_flag {
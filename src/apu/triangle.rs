@@ -35,6 +35,11 @@ impl Triangle {
         }
     }
 
+    // The reset button puts the sequencer back at the start of the waveform.
+    pub fn reset(&mut self) {
+        self.waveform_counter = 0;
+    }
+
     pub fn clock(&mut self) {
         if self.timer == 0 {
             self.timer = self.timer_period;
@@ -52,7 +52,7 @@ impl AudioCallback for ApuSampler {
 
         let mut b = self.buffer.lock().unwrap();
         // if we have data in the buffer
-        if b.len() > 0 {
+        if !b.is_empty() {
             // copy samples at the appropriate interval from the raw APU buffer to the output device
             for (i, x) in out.iter_mut().enumerate() {
                 let sample_idx = ((i as f32) * self.sample_ratio) as usize;
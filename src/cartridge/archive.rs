@@ -0,0 +1,67 @@
+// Reading ROMs from disk, either as plain files or from inside .zip archives.
+
+use super::RomError;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Nothing real comes close to this, so anything bigger is a broken or malicious archive rather than a game.
+pub const MAX_ROM_SIZE: usize = 16 * 1024 * 1024;
+
+pub fn is_zip(path: &str) -> bool {
+    has_extension(path, "zip")
+}
+
+// The games inside an archive, in the order they're stored. Empty if the file isn't a .zip. Only files that start
+// with the iNES signature count, whatever they're called, so .fds and .nsf files and readmes are left out.
+pub fn rom_entries(path: &str) -> Result<Vec<String>, RomError> {
+    if !is_zip(path) {
+        return Ok(vec![]);
+    }
+    let mut archive = open_zip(path)?;
+    let mut entries = vec![];
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(|e| RomError::Archive(e.to_string()))?;
+        let mut signature = [0; 4];
+        if file.is_file() && file.read_exact(&mut signature).is_ok() && signature == [0x4E, 0x45, 0x53, 0x1A] {
+            entries.push(file.name().to_string());
+        }
+    }
+    Ok(entries)
+}
+
+// Reads a ROM image, returning it with the filename battery saves and save states should be named after.
+// For a .zip that's the game's name from inside the archive, next to the archive, so `roms/smb.zip` holding
+// `Super Mario Bros. (World).nes` saves to `roms/Super Mario Bros. (World).sav`. Which game to take from the archive
+// can be given, otherwise it's the first one.
+pub fn read_rom_file(path: &str, entry: Option<&str>) -> Result<(Vec<u8>, String), RomError> {
+    if !is_zip(path) {
+        return Ok((std::fs::read(path)?, path.to_string()));
+    }
+    let entry = match entry {
+        Some(e) => e.to_string(),
+        None => rom_entries(path)?.into_iter().next()
+            .ok_or_else(|| RomError::Archive(format!("no NES ROM in {}", path)))?,
+    };
+    let mut archive = open_zip(path)?;
+    let mut file = archive.by_name(&entry).map_err(|e| RomError::Archive(format!("{}: {}", entry, e)))?;
+    let mut data = vec![];
+    // read one byte past the limit to tell a file that's too big from one that's exactly the limit
+    file.by_ref().take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut data)?;
+    if data.len() > MAX_ROM_SIZE {
+        return Err(RomError::Archive(format!("{} is bigger than {} MiB", entry, MAX_ROM_SIZE >> 20)));
+    }
+    let game_name = Path::new(&entry).file_name().unwrap_or_default();
+    let filename = Path::new(path).with_file_name(game_name);
+    Ok((data, filename.to_string_lossy().to_string()))
+}
+
+fn open_zip(path: &str) -> Result<zip::ZipArchive<File>, RomError> {
+    let f = File::open(path)?;
+    zip::ZipArchive::new(f).map_err(|e| RomError::Archive(e.to_string()))
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    Path::new(path).extension().is_some_and(|e| e.eq_ignore_ascii_case(extension))
+}
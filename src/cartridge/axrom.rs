@@ -0,0 +1,92 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// AxROM: one register at $8000-$FFFF. Bits 0-2 select a 32 KB PRG bank, and bit 4 which nametable is used for all four
+// screens (single-screen mirroring). The boards have CHR-RAM.
+// AMROM and AOROM have bus conflicts, where the ROM drives the data bus at the same time as the CPU, so the register
+// gets the value written ANDed with the ROM byte at that address. ANROM doesn't. NES 2.0 submapper 2 means there are
+// bus conflicts; otherwise we assume there aren't, since games work either way if they were written for them.
+// See https://wiki.nesdev.com/w/index.php/AxROM
+pub struct Axrom {
+    cart: Cartridge,
+    chr_ram: Vec<u8>,
+    prg_bank: usize,
+    mirroring: Mirror,
+}
+
+impl Axrom {
+    pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        Axrom{
+            cart,
+            chr_ram,
+            prg_bank: 0,
+            mirroring: Mirror::LowBank,
+        }
+    }
+}
+
+impl Mapper for Axrom {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size > 0 {
+                    self.cart.chr_rom[0][address]
+                } else {
+                    return read_ram(&self.chr_ram, address)
+                }
+            },
+            0x8000..=0xFFFF => {
+                // 32 KB banks are pairs of 16 KB chunks
+                let chunk = (self.prg_bank * 2 + (address - 0x8000) / 0x4000) % self.cart.prg_rom_size;
+                self.cart.prg_rom[chunk][address % 0x4000]
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x8000..=0xFFFF => {
+                let value = match self.cart.submapper {
+                    2 => value & self.read(address).unwrap_or(0xFF),
+                    _ => value,
+                };
+                self.prg_bank = (value & 0b111) as usize;
+                self.mirroring = if value & 0b1_0000 == 0 {Mirror::LowBank} else {Mirror::HighBank};
+            },
+            _ => (),
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.mirroring
+    }
+
+    fn load_battery_backed_ram(&mut self) {}
+    fn save_battery_backed_ram(&self) {}
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Axrom(
+            AxromData {
+                cart: self.cart.clone(),
+                chr_ram: self.chr_ram.clone(),
+                prg_bank: self.prg_bank,
+                mirroring: self.mirroring,
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Axrom(axrom_data) = mapper_data {
+            self.cart = axrom_data.cart;
+            self.chr_ram = axrom_data.chr_ram;
+            self.prg_bank = axrom_data.prg_bank;
+            self.mirroring = axrom_data.mirroring;
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// Mapper 34 is two unrelated boards that both switch 32 KB of PRG-ROM.
+//     BNROM: one register at $8000-$FFFF selecting the PRG bank, and 8 KB of CHR-RAM. Used by Deadly Towers.
+//     NINA-001: 8 KB of PRG-RAM at $6000-$7FFF, and registers at $7FFD (PRG bank), $7FFE (4 KB CHR bank at PPU $0000)
+//     and $7FFF (4 KB CHR bank at PPU $1000). Writes to the registers also go to the RAM underneath. Used by Impossible Mission II.
+// NES 2.0 submapper 1 is NINA-001 and 2 is BNROM. Without one, NINA-001 is the one with more than 8 KB of CHR-ROM.
+// See https://wiki.nesdev.com/w/index.php/INES_Mapper_034
+pub struct Bnrom {
+    cart: Cartridge,
+    nina_001: bool,
+    chr_ram: Vec<u8>,
+    prg_ram: Vec<u8>, // NINA-001 only
+    prg_bank: usize,
+    chr_banks: [usize; 2], // NINA-001's 4 KB banks at $0000 and $1000
+}
+
+impl Bnrom {
+    pub fn new(cart: Cartridge) -> Self {
+        let nina_001 = match cart.submapper {
+            1 => true,
+            2 => false,
+            _ => cart.chr_rom_bytes > 0x2000,
+        };
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        let mut prg_ram = vec![0; if nina_001 { cart.prg_ram_bytes() } else { 0 }];
+        cart.load_trainer(&mut prg_ram);
+        Bnrom{
+            cart,
+            nina_001,
+            chr_ram,
+            prg_ram,
+            prg_bank: 0,
+            chr_banks: [0, 1],
+        }
+    }
+}
+
+impl Mapper for Bnrom {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size == 0 {
+                    return read_ram(&self.chr_ram, address)
+                } else if self.nina_001 {
+                    let bank = self.chr_banks[address / 0x1000];
+                    self.cart.chr_rom[(bank / 2) % self.cart.chr_rom_size][(bank % 2) * 0x1000 + address % 0x1000]
+                } else {
+                    self.cart.chr_rom[0][address]
+                }
+            },
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram, address % 0x2000),
+            0x8000..=0xFFFF => {
+                let chunk = (self.prg_bank * 2 + (address - 0x8000) / 0x4000) % self.cart.prg_rom_size;
+                self.cart.prg_rom[chunk][address % 0x4000]
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x6000..=0x7FFF => {
+                write_ram(&mut self.prg_ram, address % 0x2000, value);
+                if self.nina_001 {
+                    match address {
+                        0x7FFD => self.prg_bank = (value & 1) as usize,
+                        0x7FFE => self.chr_banks[0] = (value & 0x0F) as usize,
+                        0x7FFF => self.chr_banks[1] = (value & 0x0F) as usize,
+                        _ => (),
+                    }
+                }
+            },
+            0x8000..=0xFFFF if !self.nina_001 => self.prg_bank = value as usize,
+            _ => (),
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.cart.mirroring
+    }
+
+    fn load_battery_backed_ram(&mut self) {}
+    fn save_battery_backed_ram(&self) {}
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Bnrom(
+            BnromData {
+                cart: self.cart.clone(),
+                nina_001: self.nina_001,
+                chr_ram: self.chr_ram.clone(),
+                prg_ram: self.prg_ram.clone(),
+                prg_bank: self.prg_bank,
+                chr_banks: self.chr_banks,
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Bnrom(bnrom_data) = mapper_data {
+            self.cart = bnrom_data.cart;
+            self.nina_001 = bnrom_data.nina_001;
+            self.chr_ram = bnrom_data.chr_ram;
+            self.prg_ram = bnrom_data.prg_ram;
+            self.prg_bank = bnrom_data.prg_bank;
+            self.chr_banks = bnrom_data.chr_banks;
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// Camerica/Codemasters boards: like UxROM, but the PRG bank register is at $C000-$FFFF, and $8000-$BFFF holds the
+// selected 16 KB bank while $C000-$FFFF is fixed to the last one. The boards have CHR-RAM.
+// Fire Hawk's board adds single-screen mirroring: bit 4 of a write to $8000-$9FFF selects the nametable. NES 2.0
+// submapper 1 marks that board. Without one, only writes to $9000-$9FFF change the mirroring, since other games write
+// to $8000 for their own reasons (the CIC defeat) and expect the header's mirroring to stay.
+// See https://wiki.nesdev.com/w/index.php/INES_Mapper_071
+pub struct Camerica {
+    cart: Cartridge,
+    chr_ram: Vec<u8>,
+    prg_bank: usize,
+    mirroring: Option<Mirror>, // None until the game selects a single screen
+}
+
+impl Camerica {
+    pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        Camerica{
+            cart,
+            chr_ram,
+            prg_bank: 0,
+            mirroring: None,
+        }
+    }
+}
+
+impl Mapper for Camerica {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size > 0 {
+                    self.cart.chr_rom[0][address]
+                } else {
+                    return read_ram(&self.chr_ram, address)
+                }
+            },
+            0x8000..=0xBFFF => self.cart.prg_rom[self.prg_bank % self.cart.prg_rom_size][address % 0x4000],
+            0xC000..=0xFFFF => self.cart.prg_rom[self.cart.prg_rom_size - 1][address % 0x4000],
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x8000..=0xBFFF => {
+                let fire_hawk = match self.cart.submapper {
+                    1 => address < 0xA000,
+                    _ => (0x9000..=0x9FFF).contains(&address),
+                };
+                if fire_hawk {
+                    self.mirroring = Some(if value & 0b1_0000 == 0 {Mirror::LowBank} else {Mirror::HighBank});
+                }
+            },
+            0xC000..=0xFFFF => self.prg_bank = (value & 0b1111) as usize,
+            _ => (),
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.mirroring.unwrap_or(self.cart.mirroring)
+    }
+
+    fn load_battery_backed_ram(&mut self) {}
+    fn save_battery_backed_ram(&self) {}
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Camerica(
+            CamericaData {
+                cart: self.cart.clone(),
+                chr_ram: self.chr_ram.clone(),
+                prg_bank: self.prg_bank,
+                mirroring: self.mirroring,
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Camerica(camerica_data) = mapper_data {
+            self.cart = camerica_data.cart;
+            self.chr_ram = camerica_data.chr_ram;
+            self.prg_bank = camerica_data.prg_bank;
+            self.mirroring = camerica_data.mirroring;
+        }
+    }
+}
@@ -1,35 +1,42 @@
-use super::{Cartridge, Mapper, Mirror, serialize::*};
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
 
 pub struct Cnrom {
     cart: Cartridge,
+    prg_ram: Vec<u8>,
     chr_bank_select: usize,
 }
 
 impl Cnrom {
     pub fn new(cart: Cartridge) -> Self {
+        let mut prg_ram = vec![0; cart.prg_ram_bytes()];
+        cart.load_trainer(&mut prg_ram);
         Cnrom{
-            cart: cart,
+            cart,
+            prg_ram,
             chr_bank_select: 0,
         }
     }
 }
 
 impl Mapper for Cnrom {
-    fn read(&self, address: usize) -> u8 {
+    fn read(&self, address: usize) -> Option<u8> {
         let pl = self.cart.prg_rom.len();
         let addr = address % 0x4000;
-        match address {
+        let val = match address {
             0x0000..=0x1FFF => self.cart.chr_rom[self.chr_bank_select][address],
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram, address % 0x2000),
             0x8000..=0xBFFF => self.cart.prg_rom[0][addr],
             0xC000..=0xFFFF => self.cart.prg_rom[pl-1][addr],
-            _ => {println!("bad address read from CNROM mapper: 0x{:X}", address); 0},
-        }
+            _ => return None,
+        };
+        Some(val)
     }
 
     fn write(&mut self, address: usize, value: u8) {
         match address {
+            0x6000..=0x7FFF => write_ram(&mut self.prg_ram, address % 0x2000, value),
             0x8000..=0xFFFF => self.chr_bank_select = (value & 0b11) as usize,
-            _ => println!("bad address written to CNROM mapper: 0x{:X}", address),
+            _ => (),
         }
     }
 
@@ -40,12 +47,14 @@ impl Mapper for Cnrom {
     fn load_battery_backed_ram(&mut self) {}
     fn save_battery_backed_ram(&self) {}
     fn clock(&mut self) {}
-    fn check_irq(&mut self) -> bool {false}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
 
     fn save_state(&self) -> MapperData {
         MapperData::Cnrom(
             CnromData {
                 cart: self.cart.clone(),
+                prg_ram: self.prg_ram.clone(),
                 chr_bank_select: self.chr_bank_select,
             }
         )
@@ -54,6 +63,7 @@ impl Mapper for Cnrom {
     fn load_state(&mut self, mapper_data: MapperData) {
         if let MapperData::Cnrom(cnrom_data) = mapper_data {
             self.cart = cnrom_data.cart;
+            self.prg_ram = cnrom_data.prg_ram;
             self.chr_bank_select = cnrom_data.chr_bank_select;
         }
     }
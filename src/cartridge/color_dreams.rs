@@ -0,0 +1,84 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// Color Dreams: one register at $8000-$FFFF. Bits 0-1 select a 32 KB PRG bank and bits 4-7 an 8 KB CHR bank.
+// Mirroring is fixed by the board. Wisdom Tree's games use the same board.
+// See https://wiki.nesdev.com/w/index.php/Color_Dreams
+pub struct ColorDreams {
+    cart: Cartridge,
+    chr_ram: Vec<u8>,
+    prg_bank: usize,
+    chr_bank: usize,
+}
+
+impl ColorDreams {
+    pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        ColorDreams{
+            cart,
+            chr_ram,
+            prg_bank: 0,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for ColorDreams {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size > 0 {
+                    self.cart.chr_rom[self.chr_bank % self.cart.chr_rom_size][address]
+                } else {
+                    return read_ram(&self.chr_ram, address)
+                }
+            },
+            0x8000..=0xFFFF => {
+                let chunk = (self.prg_bank * 2 + (address - 0x8000) / 0x4000) % self.cart.prg_rom_size;
+                self.cart.prg_rom[chunk][address % 0x4000]
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x8000..=0xFFFF => {
+                self.prg_bank = (value & 0b11) as usize;
+                self.chr_bank = (value >> 4) as usize;
+            },
+            _ => (),
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.cart.mirroring
+    }
+
+    fn load_battery_backed_ram(&mut self) {}
+    fn save_battery_backed_ram(&self) {}
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> MapperData {
+        MapperData::ColorDreams(
+            ColorDreamsData {
+                cart: self.cart.clone(),
+                chr_ram: self.chr_ram.clone(),
+                prg_bank: self.prg_bank,
+                chr_bank: self.chr_bank,
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::ColorDreams(color_dreams_data) = mapper_data {
+            self.cart = color_dreams_data.cart;
+            self.chr_ram = color_dreams_data.chr_ram;
+            self.prg_bank = color_dreams_data.prg_bank;
+            self.chr_bank = color_dreams_data.chr_bank;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// What a ROM's header should have said, looked up by the hash of its PRG and CHR data.
+// Lots of dumps floating around have bad iNES headers: the wrong mirroring, no battery flag, or the wrong mapper entirely.
+// The database is in the format of the NES 2.0 XML database from the nesdev forum (nes20db.xml), one <game> per ROM:
+//     <game>
+//         <!-- Game Name (USA).nes -->
+//         <rom size="40960" crc32="3337EC46" sha1="..."/>
+//         <pcb mapper="0" submapper="0" mirroring="V" battery="0"/>
+//         <prgram size="8192"/> <prgnvram .../> <chrram .../> <chrnvram .../>
+//         <console type="0" region="0"/>
+//     </game>
+// <rom> is the hash of the PRG-ROM followed by the CHR-ROM, and missing RAM elements mean there's none of that kind.
+// Other elements (<prgrom>, <chrrom>, <expansion>, etc.) are skipped. See https://forums.nesdev.org/viewtopic.php?t=19940
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+pub const DATABASE: &str = include_str!("nes20db.xml");
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameInfo {
+    pub name: String,
+    pub mapper: u16,
+    pub submapper: u8,
+    pub mirroring: Option<char>, // 'H', 'V' or '4', if the board has fixed mirroring
+    pub battery: bool,
+    pub prg_ram: usize,
+    pub prg_nvram: usize,
+    pub chr_ram: usize,
+    pub chr_nvram: usize,
+    pub region: u8, // as in NES 2.0 header byte 12: 0 NTSC, 1 PAL, 2 multi-region, 3 Dendy
+}
+
+// A parsed database, keyed by CRC32. Each entry keeps its SHA-1, if it has one, since the CRC alone can collide.
+pub struct GameDatabase {
+    games: HashMap<u32, Vec<(Option<String>, GameInfo)>>,
+}
+
+impl GameDatabase {
+    pub fn parse(xml: &str) -> Self {
+        let mut games: HashMap<u32, Vec<(Option<String>, GameInfo)>> = HashMap::new();
+        for game in xml.split("<game>").skip(1).map(|game| game.split("</game>").next().unwrap_or(game)) {
+            let crc32 = match attribute(game, "rom", "crc32").and_then(|c| u32::from_str_radix(c, 16).ok()) {
+                Some(c) => c,
+                None => continue,
+            };
+            if let Some(info) = parse_game(game) {
+                let sha1 = attribute(game, "rom", "sha1").map(|s| s.to_uppercase());
+                games.entry(crc32).or_default().push((sha1, info));
+            }
+        }
+        GameDatabase { games }
+    }
+
+    pub fn lookup(&self, crc32: u32, sha1: &str) -> Option<GameInfo> {
+        self.games.get(&crc32)?.iter()
+            .find(|(s, _)| s.as_ref().is_none_or(|s| s.eq_ignore_ascii_case(sha1)))
+            .map(|(_, info)| info.clone())
+    }
+}
+
+// The bundled database, parsed the first time a ROM is loaded.
+pub fn bundled() -> &'static GameDatabase {
+    static BUNDLED: OnceLock<GameDatabase> = OnceLock::new();
+    BUNDLED.get_or_init(|| GameDatabase::parse(DATABASE))
+}
+
+pub fn lookup(crc32: u32, sha1: &str) -> Option<GameInfo> {
+    bundled().lookup(crc32, sha1)
+}
+
+fn parse_game(game: &str) -> Option<GameInfo> {
+    let number = |tag, name| attribute(game, tag, name).and_then(|v| v.parse::<usize>().ok());
+    let name = game.split("<!--").nth(1)
+        .and_then(|c| c.split("-->").next())
+        .map(|n| n.trim().to_string())
+        .unwrap_or_default();
+    Some(GameInfo {
+        name,
+        mapper: number("pcb", "mapper")? as u16,
+        submapper: number("pcb", "submapper").unwrap_or(0) as u8,
+        mirroring: attribute(game, "pcb", "mirroring").and_then(|m| m.chars().next()).filter(|m| "HV4".contains(*m)),
+        battery: attribute(game, "pcb", "battery") == Some("1"),
+        prg_ram: number("prgram", "size").unwrap_or(0),
+        prg_nvram: number("prgnvram", "size").unwrap_or(0),
+        chr_ram: number("chrram", "size").unwrap_or(0),
+        chr_nvram: number("chrnvram", "size").unwrap_or(0),
+        region: number("console", "region").unwrap_or(0) as u8,
+    })
+}
+
+// The value of name="..." in the first <tag ...> of a game.
+fn attribute<'a>(game: &'a str, tag: &str, name: &str) -> Option<&'a str> {
+    let start = game.find(&format!("<{} ", tag))?;
+    let element = game[start..].split('>').next()?;
+    let value_start = element.find(&format!(" {}=\"", name))? + name.len() + 3;
+    element[value_start..].split('"').next()
+}
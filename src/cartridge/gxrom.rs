@@ -0,0 +1,83 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// GxROM and MHROM: one register at $8000-$FFFF. Bits 4-5 select a 32 KB PRG bank and bits 0-1 an 8 KB CHR bank.
+// Mirroring is fixed by the board. See https://wiki.nesdev.com/w/index.php/GxROM
+pub struct Gxrom {
+    cart: Cartridge,
+    chr_ram: Vec<u8>,
+    prg_bank: usize,
+    chr_bank: usize,
+}
+
+impl Gxrom {
+    pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        Gxrom{
+            cart,
+            chr_ram,
+            prg_bank: 0,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Gxrom {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size > 0 {
+                    self.cart.chr_rom[self.chr_bank % self.cart.chr_rom_size][address]
+                } else {
+                    return read_ram(&self.chr_ram, address)
+                }
+            },
+            0x8000..=0xFFFF => {
+                let chunk = (self.prg_bank * 2 + (address - 0x8000) / 0x4000) % self.cart.prg_rom_size;
+                self.cart.prg_rom[chunk][address % 0x4000]
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x8000..=0xFFFF => {
+                self.prg_bank = ((value >> 4) & 0b11) as usize;
+                self.chr_bank = (value & 0b11) as usize;
+            },
+            _ => (),
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.cart.mirroring
+    }
+
+    fn load_battery_backed_ram(&mut self) {}
+    fn save_battery_backed_ram(&self) {}
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Gxrom(
+            GxromData {
+                cart: self.cart.clone(),
+                chr_ram: self.chr_ram.clone(),
+                prg_bank: self.prg_bank,
+                chr_bank: self.chr_bank,
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Gxrom(gxrom_data) = mapper_data {
+            self.cart = gxrom_data.cart;
+            self.chr_ram = gxrom_data.chr_ram;
+            self.prg_bank = gxrom_data.prg_bank;
+            self.chr_bank = gxrom_data.chr_bank;
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// A description of a ROM for people: what the header says (after any database fixes), what board it's for,
+// and whether nestur can run it. `nestur info` prints this.
+
+use super::{Cartridge, ConsoleType, Mirror, Timing, mapper_supported};
+
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct RomInfo {
+    pub format: String, // "iNES" or "NES 2.0"
+    pub mapper: u16,
+    pub mapper_name: String,
+    pub submapper: u8,
+    pub submapper_name: Option<String>,
+    pub supported: bool, // whether nestur has this mapper
+    pub prg_rom: usize,  // sizes in bytes
+    pub chr_rom: usize,
+    pub prg_ram: usize,
+    pub prg_nvram: usize,
+    pub chr_ram: usize,
+    pub chr_nvram: usize,
+    pub mirroring: Mirror,
+    pub battery: bool,
+    pub trainer: bool,
+    pub four_screen: bool,
+    pub region: Timing,
+    pub console_type: ConsoleType,
+    pub expansion_device: u8,
+    pub crc32: String,
+    pub sha1: String,
+    pub database_fixes: Vec<String>, // header fields corrected by the game database
+}
+
+impl Cartridge {
+    pub fn info(&self) -> RomInfo {
+        RomInfo {
+            format: if self.nes2 { "NES 2.0" } else { "iNES" }.to_string(),
+            mapper: self.mapper_num,
+            mapper_name: mapper_name(self.mapper_num).to_string(),
+            submapper: self.submapper,
+            submapper_name: submapper_name(self.mapper_num, self.submapper).map(|s| s.to_string()),
+            supported: mapper_supported(self.mapper_num),
+            prg_rom: self.prg_rom_bytes,
+            chr_rom: self.chr_rom_bytes,
+            prg_ram: self.prg_ram_size,
+            prg_nvram: self.prg_nvram_size,
+            chr_ram: self.chr_ram_size,
+            chr_nvram: self.chr_nvram_size,
+            mirroring: self.mirroring,
+            battery: self.battery_backed_ram,
+            trainer: self.trainer_present,
+            four_screen: self.four_screen_vram,
+            region: self.timing,
+            console_type: self.console_type,
+            expansion_device: self.expansion_device,
+            crc32: format!("{:08X}", self.crc32),
+            sha1: self.sha1.clone(),
+            database_fixes: self.db_overrides.clone(),
+        }
+    }
+}
+
+// The usual names for the boards and chips behind common mapper numbers. See https://wiki.nesdev.com/w/index.php/Mapper
+pub fn mapper_name(num: u16) -> &'static str {
+    match num {
+        0 => "NROM",
+        1 => "MMC1",
+        2 => "UxROM",
+        3 => "CNROM",
+        4 => "MMC3",
+        5 => "MMC5",
+        7 => "AxROM",
+        9 => "MMC2",
+        10 => "MMC4",
+        11 => "Color Dreams",
+        13 => "CPROM",
+        16 => "Bandai FCG",
+        19 => "Namco 163",
+        21 | 22 | 23 | 25 => "Konami VRC2/VRC4",
+        24 | 26 => "Konami VRC6",
+        34 => "BNROM/NINA-001",
+        66 => "GxROM",
+        69 => "Sunsoft FME-7",
+        71 => "Camerica/Codemasters",
+        79 => "NINA-03/NINA-06",
+        85 => "Konami VRC7",
+        206 => "Namco 118",
+        _ => "unknown",
+    }
+}
+
+// Submappers that tell boards apart, from https://wiki.nesdev.com/w/index.php/NES_2.0_submappers
+pub fn submapper_name(mapper: u16, submapper: u8) -> Option<&'static str> {
+    let name = match (mapper, submapper) {
+        (1, 5) => "SEROM/SHROM/SH1ROM",
+        (2, 1) | (3, 1) | (7, 1) => "no bus conflicts",
+        (2, 2) | (3, 2) | (7, 2) => "AND-type bus conflicts",
+        (4, 1) => "MMC6",
+        (4, 3) => "MC-ACC",
+        (4, 4) => "MMC3A",
+        (34, 1) => "NINA-001",
+        (34, 2) => "BNROM",
+        (71, 1) => "Fire Hawk",
+        _ => return None,
+    };
+    Some(name)
+}
@@ -1,8 +1,4 @@
-use super::{Cartridge, Mapper, Mirror, serialize::*};
-
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
 
 pub struct Mmc1 {
     cart: Cartridge,
@@ -17,7 +13,7 @@ pub struct Mmc1 {
     prg_bank_mode: u8,
     prg_bank_select: usize, // selects among the PRG-RAM chunks in the cartridge
 
-    chr_ram_bank: Vec<u8>, // used if cartridge doesn't have any CHR-ROM, 8KB, $0000-$1FFF
+    chr_ram_bank: Vec<u8>, // used if cartridge doesn't have any CHR-ROM, usually 8KB, $0000-$1FFF
     chr_low_bank: usize,   // PPU $0000-$0FFF
     chr_high_bank: usize,  // PPU $1000-$1FFF
     chr_bank_mode: bool,   // false: switch 8 KB at a time; true: switch two separate 4 KB banks
@@ -26,22 +22,25 @@ pub struct Mmc1 {
 impl Mmc1 {
     pub fn new(cart: Cartridge) -> Self {
         let m = cart.mirroring;
+        let prg_ram = vec![0; cart.prg_ram_bytes()];
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
         let mut mmc1 = Mmc1 {
-            cart: cart,
+            cart,
             step: 0,
             shift_register: 0,
             mirroring: m,
             control: 0,
-            prg_ram_bank: vec![0; 0x2000],
+            prg_ram_bank: prg_ram,
             prg_ram_enabled: false,
             prg_bank_mode: 3,
             prg_bank_select: 0,
-            chr_ram_bank: vec![0; 0x2000],
+            chr_ram_bank: chr_ram,
             chr_low_bank: 0,
             chr_high_bank: 0,
             chr_bank_mode: false,
         };
         mmc1.load_battery_backed_ram();
+        mmc1.cart.load_trainer(&mut mmc1.prg_ram_bank);
         mmc1
     }
 
@@ -85,7 +84,7 @@ impl Mmc1 {
             _ => panic!("invalid mirroring value"),
         };
         self.prg_bank_mode = (value >> 2) & 0b11;
-        self.chr_bank_mode = if value & (1<<4) == 0 {false} else {true};
+        self.chr_bank_mode = value & (1<<4) != 0;
     }
 
     fn write_chr_bank_low(&mut self, value: u8) {
@@ -111,11 +110,11 @@ impl Mmc1 {
 }
 
 impl Mapper for Mmc1 {
-    fn read(&self, address: usize) -> u8 {
-        match address {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
             0x0000..=0x1FFF => {
                 if self.cart.chr_rom_size == 0 {
-                    self.chr_ram_bank[address]
+                    return read_ram(&self.chr_ram_bank, address)
                 } else {
                     let offset = address % 0x1000;
                     if self.chr_bank_mode {
@@ -135,7 +134,7 @@ impl Mapper for Mmc1 {
                     }
                 }
             },
-            0x6000..=0x7FFF => self.prg_ram_bank[address % 0x2000],
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram_bank, address % 0x2000),
             0x8000..=0xBFFF => {
                 match self.prg_bank_mode {
                     0 | 1 => { // switch 32 KB at $8000, ignoring low bit of bank number
@@ -158,20 +157,17 @@ impl Mapper for Mmc1 {
                     _ => panic!("invalid PRG bank mode"),
                 }
             },
-            _ => panic!("invalid address passed to MMC1: 0x{:X}", address),
-        }
+            _ => return None,
+        };
+        Some(val)
     }
 
     fn write(&mut self, address: usize, value: u8) {
         match address {
-            0x0000..=0x1FFF => { // if we don't have CHR-ROM, write to CHR-RAM
-                if self.cart.chr_rom_size == 0 {
-                    self.chr_ram_bank[address] = value;
-                }
-            },
-            0x6000..=0x7FFF => self.prg_ram_bank[address % 0x2000] = value,
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram_bank, address, value), // CHR-RAM
+            0x6000..=0x7FFF => write_ram(&mut self.prg_ram_bank, address % 0x2000, value),
             0x8000..=0xFFFF => self.write_serial_port(address, value),
-            _ => panic!("bad address write to MMC1: 0x{:X}", address),
+            _ => (),
         }
     }
 
@@ -180,36 +176,16 @@ impl Mapper for Mmc1 {
     }
 
     fn load_battery_backed_ram(&mut self) {
-        if self.cart.battery_backed_ram {
-            let p = Path::new(&self.cart.filename).parent().unwrap();
-            let stem = Path::new(&self.cart.filename).file_stem().unwrap();
-            let mut save_file = p.join(stem);
-            save_file.set_extension("sav");
-            if Path::new(&save_file).exists() {
-                let mut f = File::open(save_file.clone()).expect("save file exists but could not open it");
-                let mut battery_backed_ram_data = vec![];
-                f.read_to_end(&mut battery_backed_ram_data).expect("error reading save file");
-                println!("loading battery-backed RAM from file: {:?}", save_file);
-                self.prg_ram_bank = battery_backed_ram_data;
-            }
-        }
+        self.cart.load_battery_backed_ram(&mut self.prg_ram_bank);
     }
 
     fn save_battery_backed_ram(&self) {
-        if self.cart.battery_backed_ram {
-            let p = Path::new(&self.cart.filename).parent().unwrap();
-            let stem = Path::new(&self.cart.filename).file_stem().unwrap();
-            let mut save_file = p.join(stem);
-            save_file.set_extension("sav");
-            println!("saving battery-backed RAM to file: {:?}", save_file);
-            let mut f = File::create(&save_file)
-                .expect("could not create output file for battery-backed RAM");
-            f.write_all(&self.prg_ram_bank).expect("could not write battery-backed RAM to file");
-        }
+        self.cart.save_battery_backed_ram(&self.prg_ram_bank);
     }
 
     fn clock(&mut self) {}
-    fn check_irq(&mut self) -> bool {false}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
 
     fn save_state(&self) -> MapperData {
         MapperData::Mmc1(
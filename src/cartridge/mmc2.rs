@@ -0,0 +1,153 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// MMC2 (mapper 9, Punch-Out!!) and MMC4 (mapper 10, Fire Emblem and Famicom Wars), which differ only in PRG banking:
+//     MMC2: $8000-$9FFF is a switchable 8 KB bank, and $A000-$FFFF is fixed to the last three.
+//     MMC4: $8000-$BFFF is a switchable 16 KB bank, $C000-$FFFF is fixed to the last one, and there's 8 KB of
+//           (usually battery-backed) PRG-RAM at $6000-$7FFF.
+// Each half of the pattern tables has two 4 KB CHR banks to choose from, and a latch that chooses between them.
+// The latches are set by the PPU itself: "When the PPU reads from specific tiles in the pattern table during rendering,
+// the MMC2 will swap in a different CHR bank." Fetching the last byte of tile $FD selects the first bank,
+// and of tile $FE the second, for the rest of the frame or until the other tile is fetched. The fetch that trips
+// the latch still gets the old bank.
+// See https://wiki.nesdev.com/w/index.php/MMC2 and https://wiki.nesdev.com/w/index.php/MMC4
+pub struct Mmc2 {
+    cart: Cartridge,
+    mmc4: bool,
+    mirroring: Mirror,
+    prg_bank: usize,
+    chr_banks: [[usize; 2]; 2], // [pattern table][latch], in 4 KB banks
+    latches: [usize; 2], // 0 for $FD, 1 for $FE
+    prg_ram_bank: Vec<u8>, // MMC4 only
+    chr_ram_bank: Vec<u8>,
+}
+
+impl Mmc2 {
+    pub fn new(cart: Cartridge) -> Self {
+        let mmc4 = cart.mapper_num == 10;
+        let mut prg_ram = vec![0; if mmc4 { cart.prg_ram_bytes() } else { 0 }];
+        cart.load_trainer(&mut prg_ram);
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        let m = cart.mirroring;
+        Mmc2{
+            cart,
+            mmc4,
+            mirroring: m,
+            prg_bank: 0,
+            chr_banks: [[0, 0], [0, 0]],
+            latches: [1, 1],
+            prg_ram_bank: prg_ram,
+            chr_ram_bank: chr_ram,
+        }
+    }
+}
+
+impl Mapper for Mmc2 {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size == 0 {
+                    return read_ram(&self.chr_ram_bank, address)
+                }
+                let table = address / 0x1000;
+                let bank = self.chr_banks[table][self.latches[table]];
+                // 4 KB banks are halves of 8 KB chunks
+                self.cart.chr_rom[(bank / 2) % self.cart.chr_rom_size][(bank % 2) * 0x1000 + address % 0x1000]
+            },
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram_bank, address % 0x2000),
+            0x8000..=0xFFFF => {
+                if self.mmc4 {
+                    let chunk = match address {
+                        0x8000..=0xBFFF => self.prg_bank % self.cart.prg_rom_size,
+                        _ => self.cart.prg_rom_size - 1,
+                    };
+                    self.cart.prg_rom[chunk][address % 0x4000]
+                } else {
+                    // 8 KB banks are halves of 16 KB chunks
+                    let num_banks = self.cart.prg_rom_size * 2;
+                    let bank_num = match address {
+                        0x8000..=0x9FFF => self.prg_bank % num_banks,
+                        _ => num_banks - 3 + (address - 0xA000) / 0x2000,
+                    };
+                    self.cart.prg_rom[bank_num / 2][(bank_num % 2) * 0x2000 + address % 0x2000]
+                }
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram_bank, address, value),
+            0x6000..=0x7FFF => write_ram(&mut self.prg_ram_bank, address % 0x2000, value),
+            0xA000..=0xAFFF => self.prg_bank = (value & 0x0F) as usize,
+            0xB000..=0xBFFF => self.chr_banks[0][0] = (value & 0x1F) as usize,
+            0xC000..=0xCFFF => self.chr_banks[0][1] = (value & 0x1F) as usize,
+            0xD000..=0xDFFF => self.chr_banks[1][0] = (value & 0x1F) as usize,
+            0xE000..=0xEFFF => self.chr_banks[1][1] = (value & 0x1F) as usize,
+            0xF000..=0xFFFF => self.mirroring = if value & 1 == 0 {Mirror::Vertical} else {Mirror::Horizontal},
+            0x8000..=0x9FFF => (),
+            _ => (),
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.mirroring
+    }
+
+    fn load_battery_backed_ram(&mut self) {
+        self.cart.load_battery_backed_ram(&mut self.prg_ram_bank);
+    }
+
+    fn save_battery_backed_ram(&self) {
+        self.cart.save_battery_backed_ram(&self.prg_ram_bank);
+    }
+
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    // The MMC2 only watches for $0FD8 in the left pattern table, where the MMC4 takes the whole row like on the right.
+    fn ppu_read(&mut self, address: usize) {
+        let latch = match address {
+            0x0FD8 => Some((0, 0)),
+            0x0FE8 => Some((0, 1)),
+            0x0FD9..=0x0FDF if self.mmc4 => Some((0, 0)),
+            0x0FE9..=0x0FEF if self.mmc4 => Some((0, 1)),
+            0x1FD8..=0x1FDF => Some((1, 0)),
+            0x1FE8..=0x1FEF => Some((1, 1)),
+            _ => None,
+        };
+        if let Some((table, value)) = latch {
+            self.latches[table] = value;
+        }
+    }
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Mmc2(
+            Mmc2Data {
+                cart: self.cart.clone(),
+                mmc4: self.mmc4,
+                mirroring: self.mirroring,
+                prg_bank: self.prg_bank,
+                chr_banks: self.chr_banks,
+                latches: self.latches,
+                prg_ram_bank: self.prg_ram_bank.clone(),
+                chr_ram_bank: self.chr_ram_bank.clone(),
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Mmc2(mmc2_data) = mapper_data {
+            self.cart = mmc2_data.cart;
+            self.mmc4 = mmc2_data.mmc4;
+            self.mirroring = mmc2_data.mirroring;
+            self.prg_bank = mmc2_data.prg_bank;
+            self.chr_banks = mmc2_data.chr_banks;
+            self.latches = mmc2_data.latches;
+            self.prg_ram_bank = mmc2_data.prg_ram_bank;
+            self.chr_ram_bank = mmc2_data.chr_ram_bank;
+        }
+    }
+}
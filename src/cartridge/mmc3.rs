@@ -1,4 +1,4 @@
-use super::{Cartridge, Mapper, Mirror, serialize::*};
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
 
 pub struct Mmc3 {
     cart: Cartridge,
@@ -12,7 +12,6 @@ pub struct Mmc3 {
     irq_enable: bool,
     trigger_irq: bool, // signal to send to CPU
     reload_counter: bool,
-    irq_delay: u8,
 
     prg_ram_bank: Vec<u8>, // CPU $6000-$7FFF
     // 0: $8000-$9FFF swappable, $C000-$DFFF fixed to second-last bank
@@ -22,14 +21,17 @@ pub struct Mmc3 {
     // 1: two 2 KB banks at $1000-$1FFF, four 1 KB banks at $0000-$0FFF
 
     chr_rom_bank_mode: bool,
-    chr_ram_bank: Vec<u8>, // used if cartridge doesn't have any CHR-ROM, 8KB, $0000-$1FFF
+    chr_ram_bank: Vec<u8>, // used if cartridge doesn't have any CHR-ROM, usually 8KB, $0000-$1FFF
 }
 
 impl Mmc3 {
     pub fn new(cart: Cartridge) -> Self {
         let m = cart.mirroring;
+        let mut prg_ram = vec![0; cart.prg_ram_bytes()];
+        cart.load_trainer(&mut prg_ram);
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
         Mmc3{
-            cart: cart,
+            cart,
             mirroring: m,
             bank_registers: vec![0, 0, 0, 0, 0, 0, 0, 0],
             next_bank: 0,
@@ -38,11 +40,10 @@ impl Mmc3 {
             irq_enable: false,
             trigger_irq: false,
             reload_counter: false,
-            irq_delay: 0,
-            prg_ram_bank: vec![0; 0x2000],
+            prg_ram_bank: prg_ram,
             prg_rom_bank_mode: false,
             chr_rom_bank_mode: false,
-            chr_ram_bank: vec![0; 0x2000],
+            chr_ram_bank: chr_ram,
         }
     }
 
@@ -67,7 +68,7 @@ impl Mmc3 {
 }
 
 impl Mapper for Mmc3 {
-    fn read(&self, address: usize) -> u8 {
+    fn read(&self, address: usize) -> Option<u8> {
         let val = match address {
             0x0000..=0x1FFF => { // reading from CHR-ROM
                 let offset_1k = address % 0x400;
@@ -106,7 +107,7 @@ impl Mapper for Mmc3 {
                 }
             },
 
-            0x6000..=0x7FFF => self.prg_ram_bank[address % 0x2000], // PRG-RAM
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram_bank, address % 0x2000), // PRG-RAM
 
             0x8000..=0xFFFF => { // reading from PRG ROM, dealing with 8K banks of 16K chunks
                 let offset_8k = address % 0x2000;
@@ -136,40 +137,37 @@ impl Mapper for Mmc3 {
                 self.cart.prg_rom[chunk_num][chunk_half + offset_8k]
 
             },
-            _ => {
-                println!("bad address read from MMC3: 0x{:X}", address);
-                0
-            },
+            _ => return None,
         };
-        val
+        Some(val)
     }
 
     fn write(&mut self, address: usize, value: u8) {
         if (0..=0x1FFF).contains(&address) {
             if self.cart.chr_rom_size == 0 {
-                self.chr_ram_bank[address] = value;
+                write_ram(&mut self.chr_ram_bank, address, value);
             }
             return
         }
-        match address % 2 == 0 {
+        match address & 1 == 0 {
             true => { // even
                 match address {
-                    0x6000..=0x7FFF => self.prg_ram_bank[address % 0x2000] = value, // PRG-RAM
+                    0x6000..=0x7FFF => write_ram(&mut self.prg_ram_bank, address % 0x2000, value), // PRG-RAM
                     0x8000..=0x9FFF => self.bank_select(value),
                     0xA000..=0xBFFF => self.mirroring = if value & 1 == 0 {Mirror::Vertical} else {Mirror::Horizontal},
                     0xC000..=0xDFFF => self.irq_latch = value,
                     0xE000..=0xFFFF => {self.irq_enable = false; self.trigger_irq = false}, // Writing any value to this register will disable MMC3 interrupts AND acknowledge any pending interrupts.
-                    _ => println!("bad address written to MMC3: 0x{:X}", address),
+                    _ => (),
                 }
             },
             false => { // odd
                 match address {
-                    0x6000..=0x7FFF => self.prg_ram_bank[address % 0x2000] = value, // PRG-RAM
+                    0x6000..=0x7FFF => write_ram(&mut self.prg_ram_bank, address % 0x2000, value), // PRG-RAM
                     0x8000..=0x9FFF => self.bank_data(value),
                     0xA000..=0xBFFF => self.prg_ram_protect(),
                     0xC000..=0xDFFF => self.reload_counter = true, // Writing any value to this register reloads the MMC3 IRQ counter at the NEXT rising edge of the PPU address, presumably at PPU cycle 260 of the current scanline.
                     0xE000..=0xFFFF => self.irq_enable = true,
-                    _ => println!("bad address written to MMC3: 0x{:X}", address),
+                    _ => (),
                 }
             },
         }
@@ -204,25 +202,13 @@ impl Mapper for Mmc3 {
         }
     }
 
-    // This function is called by the CPU every step (which takes more than one CPU clock cycle).
-    // I think I'm supposed to be tracking IRQ delays by the PPU, not letting an IRQ fire if
-    // there was one within the last 15 PPU cycles, but that didn't work and this does.
-    fn check_irq(&mut self) -> bool {
-        if self.trigger_irq {
-            self.trigger_irq = false;
-            if self.irq_delay == 0 {
-                self.irq_delay = 5;
-            }
-        }
-        if self.irq_delay > 0 {
-            self.irq_delay -= 1;
-            if self.irq_delay == 0 {
-                return true;
-            }
-        }
-        false
+    // The IRQ stays asserted until it's acknowledged by a write to $E000.
+    fn irq_asserted(&self) -> bool {
+        self.trigger_irq
     }
 
+    fn reset(&mut self) {}
+
     fn save_state(&self) -> MapperData {
         MapperData::Mmc3(
             Mmc3Data {
@@ -235,7 +221,6 @@ impl Mapper for Mmc3 {
                 irq_enable: self.irq_enable,
                 trigger_irq: self.trigger_irq,
                 reload_counter: self.reload_counter,
-                irq_delay: self.irq_delay,
                 prg_ram_bank: self.prg_ram_bank.clone(),
                 prg_rom_bank_mode: self.prg_rom_bank_mode,
                 chr_rom_bank_mode: self.chr_rom_bank_mode,
@@ -255,7 +240,6 @@ impl Mapper for Mmc3 {
             self.irq_enable = mmc3_data.irq_enable;
             self.trigger_irq = mmc3_data.trigger_irq;
             self.reload_counter = mmc3_data.reload_counter;
-            self.irq_delay = mmc3_data.irq_delay;
             self.prg_ram_bank = mmc3_data.prg_ram_bank;
             self.prg_rom_bank_mode = mmc3_data.prg_rom_bank_mode;
             self.chr_rom_bank_mode = mmc3_data.chr_rom_bank_mode;
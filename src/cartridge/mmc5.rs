@@ -0,0 +1,501 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+use crate::apu::square::Square;
+
+// MMC5 (ExROM): Castlevania III, the Koei strategy games, Just Breed, Metal Slader Glory.
+// The most capable of Nintendo's mappers. Besides PRG and CHR banking in four sizes each, it has:
+//     1 KB of ExRAM ($5C00-$5FFF), which can be an extra nametable, extended attributes (a CHR bank and palette per
+//         background tile), or plain RAM for the CPU.
+//     Nametables chosen one by one from the console's two pages of VRAM, ExRAM, or a "fill mode" tile and color.
+//     A vertical split: a strip on the left or right of the screen drawn from ExRAM with its own scroll and CHR bank.
+//     A scanline counter and IRQ, an 8x8 multiplier, and two pulse channels plus an 8-bit PCM channel.
+// The chip watches the PPU's bus to know what's being fetched, which here comes from ppu_tick() and the nametable hooks.
+// See https://wiki.nesdev.com/w/index.php/MMC5
+pub struct Mmc5 {
+    cart: Cartridge,
+    prg_ram: Vec<u8>, // CPU $6000-$7FFF, and $8000-$DFFF in some PRG modes
+    chr_ram: Vec<u8>,
+    exram: Vec<u8>,
+
+    prg_mode: u8,          // $5100: 0 one 32 KB bank, 1 two 16 KB, 2 16 KB + two 8 KB, 3 four 8 KB
+    chr_mode: u8,          // $5101: 0 8 KB banks, 1 4 KB, 2 2 KB, 3 1 KB
+    prg_ram_protect: [u8; 2], // $5102/$5103: PRG-RAM can only be written while these are 2 and 1
+    exram_mode: u8,        // $5104: 0 nametable, 1 extended attributes, 2 CPU RAM, 3 CPU read-only RAM
+    nametables: [u8; 4],   // $5105: 0 VRAM page 0, 1 VRAM page 1, 2 ExRAM, 3 fill mode, for each nametable
+    fill_tile: u8,         // $5106
+    fill_color: u8,        // $5107
+    prg_banks: [u8; 5],    // $5113-$5117. Bit 7 selects ROM instead of RAM, and is always set for $5117.
+    chr_banks: [usize; 12], // $5120-$5127 for sprites (and everything in 8x8 sprite mode), $5128-$512B for background
+    chr_upper: usize,      // $5130: the top bits of the next CHR bank written
+    last_chr_background: bool, // whether $5128-$512B were written last, which decides the banks used outside rendering
+    split_control: u8,     // $5200: bit 7 enable, bit 6 right side, bits 0-4 the tile column where the split starts/ends
+    split_scroll: u8,      // $5201
+    split_bank: usize,     // $5202: a 4 KB CHR bank
+    irq_compare: u8,       // $5203
+    irq_enabled: bool,     // $5204
+    multiplicands: [u8; 2], // $5205/$5206
+
+    // following the PPU
+    scanline: usize,
+    dot: usize,
+    rendering: bool,
+    tall_sprites: bool,
+    in_frame: bool,
+    scanline_counter: u8,
+    irq_pending: bool,
+    tile_exram: u8,               // the ExRAM byte for the background tile being fetched, for extended attributes
+    split_column: Option<usize>,  // the column of the background tile being fetched, if it's in the split
+    split_y: usize,               // the split's scroll on the line being fetched
+
+    // audio
+    pulse1: Square,
+    pulse2: Square,
+    audio_timer: usize,
+    pcm: u8,
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq: bool,
+}
+
+// The pulses' envelopes and length counters run off their own 240 Hz timer instead of the APU's frame counter.
+const AUDIO_FRAME_PERIOD: usize = 3728; // APU cycles
+
+impl Mmc5 {
+    pub fn new(cart: Cartridge) -> Self {
+        // iNES headers don't say how much PRG-RAM there is. The Koei games use up to 32 KB, but some boards split theirs
+        // between two chips picked by bit 2 of the bank number, so give those 64 KB, where every bank number has
+        // RAM of its own. Banks wrap around in smaller RAM.
+        let prg_ram_size = if cart.nes2 { cart.prg_ram_bytes() } else { cart.prg_ram_bytes().max(0x10000) };
+        let mut prg_ram = vec![0; prg_ram_size];
+        cart.load_trainer(&mut prg_ram);
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        Mmc5{
+            cart,
+            prg_ram,
+            chr_ram,
+            exram: vec![0; 0x400],
+            prg_mode: 3,
+            chr_mode: 0,
+            prg_ram_protect: [0, 0],
+            exram_mode: 0,
+            nametables: [0, 0, 0, 0],
+            fill_tile: 0,
+            fill_color: 0,
+            prg_banks: [0, 0, 0, 0, 0xFF], // the last bank at $E000, where the reset vector is
+            chr_banks: [0; 12],
+            chr_upper: 0,
+            last_chr_background: false,
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+            irq_compare: 0,
+            irq_enabled: false,
+            multiplicands: [0xFF, 0xFF],
+            scanline: 0,
+            dot: 0,
+            rendering: false,
+            tall_sprites: false,
+            in_frame: false,
+            scanline_counter: 0,
+            irq_pending: false,
+            tile_exram: 0,
+            split_column: None,
+            split_y: 0,
+            pulse1: Square::without_sweep(),
+            pulse2: Square::without_sweep(),
+            audio_timer: 0,
+            pcm: 0,
+            pcm_read_mode: false,
+            pcm_irq_enabled: false,
+            pcm_irq: false,
+        }
+    }
+
+    // Which 8 KB bank of PRG-ROM (true) or PRG-RAM (false) is at a CPU address.
+    fn prg_bank(&self, address: usize) -> (bool, usize) {
+        let (register, size) = match (self.prg_mode, address) {
+            (_, 0x6000..=0x7FFF) => return (false, (self.prg_banks[0] & 0b111) as usize),
+            (0, _) => (4, 0x8000),
+            (1, 0x8000..=0xBFFF) | (2, 0x8000..=0xBFFF) => (2, 0x4000),
+            (1, _) => (4, 0x4000),
+            (2, 0xC000..=0xDFFF) => (3, 0x2000),
+            (2, _) => (4, 0x2000),
+            (_, _) => (1 + (address - 0x8000) / 0x2000, 0x2000),
+        };
+        let value = self.prg_banks[register];
+        // larger banks ignore the bottom bits of the register and take them from the address
+        let bank = ((value & 0x7F) as usize & !(size / 0x2000 - 1)) | ((address % size) / 0x2000);
+        (value & 0x80 != 0, bank)
+    }
+
+    // The PPU fetches each background tile's nametable byte on dot 1 of 8, its attribute byte on dot 3
+    // and its pattern bytes on dots 5 and 7, for the next line's first two tiles on dots 321-336 and
+    // for the rest on dots 1-256. Sprites' patterns are fetched on dots 257-320.
+    fn fetching_background(&self) -> bool {
+        self.rendering && (self.scanline < 240 || self.scanline == 261)
+            && matches!(self.dot, 1..=256 | 321..=336)
+    }
+
+    fn fetching_sprites(&self) -> bool {
+        self.rendering && self.scanline < 240 && matches!(self.dot, 257..=320)
+    }
+
+    // Where a PPU pattern table address is in CHR memory.
+    fn chr_offset(&self, address: usize) -> usize {
+        if self.fetching_background() {
+            if self.split_column.is_some() {
+                // the split's own bank, and its own fine Y scroll instead of the PPU's
+                return self.split_bank * 0x1000 + ((address & 0x0FF8) | (self.split_y % 8))
+            }
+            if self.exram_mode == 1 {
+                let bank = (self.tile_exram & 0x3F) as usize | self.chr_upper << 6;
+                return bank * 0x1000 + address % 0x1000
+            }
+        }
+        // With 8x16 sprites, sprites and background have separate banks. Otherwise everything uses the sprite banks.
+        let background = self.tall_sprites && match (self.fetching_sprites(), self.fetching_background()) {
+            (true, _) => false,
+            (_, true) => true,
+            _ => self.last_chr_background,
+        };
+        // the background banks are arranged like the sprite banks for $1000-$1FFF, and used for both halves
+        let size = 0x2000 >> self.chr_mode;
+        let a = if background { 0x1000 | (address % 0x1000) } else { address };
+        let register = (a / size + 1) * (8 >> self.chr_mode) - 1 + if background { 4 } else { 0 };
+        self.chr_banks[register] * size + address % size
+    }
+
+    fn read_chr(&self, offset: usize) -> Option<u8> {
+        if self.cart.chr_rom_size == 0 {
+            return read_ram(&self.chr_ram, offset)
+        }
+        let offset = offset % (self.cart.chr_rom_size * 0x2000);
+        Some(self.cart.chr_rom[offset / 0x2000][offset % 0x2000])
+    }
+
+    fn prg_ram_writable(&self) -> bool {
+        self.prg_ram_protect == [0b10, 0b01]
+    }
+
+    // "Upon detecting the start of a new scanline: if the in-frame flag is clear, set it, reset the scanline counter
+    // and clear the IRQ pending flag. Otherwise increment the counter, and if it equals $5203, set the pending flag."
+    fn start_scanline(&mut self) {
+        if !self.in_frame {
+            self.in_frame = true;
+            self.scanline_counter = 0;
+            self.irq_pending = false;
+        } else {
+            self.scanline_counter = self.scanline_counter.wrapping_add(1);
+            if self.scanline_counter == self.irq_compare {
+                self.irq_pending = true;
+            }
+        }
+    }
+
+    fn write_audio(&mut self, address: usize, value: u8) {
+        match address {
+            0x5000 => self.pulse1.write_duty(value),
+            0x5002 => self.pulse1.write_timer_low(value),
+            0x5003 => self.pulse1.write_timer_high(value),
+            0x5004 => self.pulse2.write_duty(value),
+            0x5006 => self.pulse2.write_timer_low(value),
+            0x5007 => self.pulse2.write_timer_high(value),
+            0x5001 | 0x5005 => (), // no sweep units
+            0x5010 => {
+                self.pcm_read_mode = value & 1 != 0;
+                self.pcm_irq_enabled = value & 0x80 != 0;
+            },
+            // "Writing $00 has no effect", since in read mode a $00 read is what raises the IRQ
+            0x5011 if !self.pcm_read_mode && value != 0 => self.pcm = value,
+            0x5015 => {
+                for (pulse, bit) in [(&mut self.pulse1, 0), (&mut self.pulse2, 1)] {
+                    pulse.enabled = value & (1 << bit) != 0;
+                    if !pulse.enabled {
+                        pulse.length_counter = 0;
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+impl Mapper for Mmc5 {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => return self.read_chr(self.chr_offset(address)),
+            0x5010 => (self.pcm_irq as u8) << 7 | self.pcm_read_mode as u8,
+            0x5015 => (self.pulse1.length_counter != 0) as u8 | ((self.pulse2.length_counter != 0) as u8) << 1,
+            0x5204 => (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6,
+            0x5205 => (self.multiplicands[0] as u16 * self.multiplicands[1] as u16) as u8,
+            0x5206 => ((self.multiplicands[0] as u16 * self.multiplicands[1] as u16) >> 8) as u8,
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[address - 0x5C00],
+            0x6000..=0xFFFF => {
+                let (rom, bank) = self.prg_bank(address);
+                if rom {
+                    let bank = bank % (self.cart.prg_rom_size * 2);
+                    self.cart.prg_rom[bank / 2][(bank % 2) * 0x2000 + address % 0x2000]
+                } else {
+                    return read_ram(&self.prg_ram, bank * 0x2000 + address % 0x2000)
+                }
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => {
+                let offset = self.chr_offset(address);
+                write_ram(&mut self.chr_ram, offset, value);
+            },
+            0x5000..=0x5015 => self.write_audio(address, value),
+            0x5100 => self.prg_mode = value & 0b11,
+            0x5101 => self.chr_mode = value & 0b11,
+            0x5102 => self.prg_ram_protect[0] = value & 0b11,
+            0x5103 => self.prg_ram_protect[1] = value & 0b11,
+            0x5104 => self.exram_mode = value & 0b11,
+            0x5105 => {
+                for (i, nametable) in self.nametables.iter_mut().enumerate() {
+                    *nametable = (value >> (i * 2)) & 0b11;
+                }
+            },
+            0x5106 => self.fill_tile = value,
+            0x5107 => self.fill_color = value & 0b11,
+            0x5113..=0x5116 => self.prg_banks[address - 0x5113] = value,
+            0x5117 => self.prg_banks[4] = value | 0x80,
+            0x5120..=0x512B => {
+                self.chr_banks[address - 0x5120] = value as usize | self.chr_upper << 8;
+                self.last_chr_background = address >= 0x5128;
+            },
+            0x5130 => self.chr_upper = (value & 0b11) as usize,
+            0x5200 => self.split_control = value,
+            0x5201 => self.split_scroll = value,
+            0x5202 => self.split_bank = value as usize,
+            0x5203 => self.irq_compare = value,
+            0x5204 => self.irq_enabled = value & 0x80 != 0,
+            0x5205 => self.multiplicands[0] = value,
+            0x5206 => self.multiplicands[1] = value,
+            0x5C00..=0x5FFF => match self.exram_mode {
+                // while ExRAM belongs to the PPU, the CPU can only write to it during rendering, and otherwise writes 0
+                0 | 1 => self.exram[address - 0x5C00] = if self.in_frame { value } else { 0 },
+                2 => self.exram[address - 0x5C00] = value,
+                _ => (),
+            },
+            0x6000..=0xFFFF => {
+                let (rom, bank) = self.prg_bank(address);
+                if !rom && self.prg_ram_writable() {
+                    write_ram(&mut self.prg_ram, bank * 0x2000 + address % 0x2000, value);
+                }
+            },
+            _ => (),
+        }
+    }
+
+    // the nametables are all handled by read_nametable() and write_nametable(), except for pages of VRAM
+    fn get_mirroring(&self) -> Mirror {
+        Mirror::Custom(self.nametables.map(|n| n & 1))
+    }
+
+    fn load_battery_backed_ram(&mut self) {
+        self.cart.load_battery_backed_ram(&mut self.prg_ram);
+    }
+
+    fn save_battery_backed_ram(&self) {
+        self.cart.save_battery_backed_ram(&self.prg_ram);
+    }
+
+    fn clock(&mut self) {}
+
+    fn irq_asserted(&self) -> bool {
+        (self.irq_pending && self.irq_enabled) || (self.pcm_irq && self.pcm_irq_enabled)
+    }
+
+    fn reset(&mut self) {}
+
+    fn cpu_read(&mut self, address: usize) {
+        match address {
+            0x5010 => self.pcm_irq = false,
+            0x5204 => self.irq_pending = false, // acknowledges the IRQ
+            // in read mode, the PCM channel plays whatever the CPU reads from $8000-$BFFF, and a $00 raises the IRQ
+            0x8000..=0xBFFF if self.pcm_read_mode => match self.read(address) {
+                Some(0) => self.pcm_irq = true,
+                Some(value) => self.pcm = value,
+                None => (),
+            },
+            _ => (),
+        }
+    }
+
+    fn read_nametable(&mut self, address: usize) -> Option<u8> {
+        let offset = address % 0x400;
+        if self.fetching_background() {
+            match self.dot % 8 {
+                1 => { // a new tile
+                    let (tile, line) = match self.dot {
+                        321..=336 => ((self.dot - 321) / 8, if self.scanline == 261 { 0 } else { self.scanline + 1 }),
+                        _ => ((self.dot - 1) / 8 + 2, self.scanline),
+                    };
+                    let threshold = (self.split_control & 0x1F) as usize;
+                    let right_side = self.split_control & 0x40 != 0;
+                    self.split_column = match self.split_control & 0x80 != 0 && self.exram_mode <= 1 {
+                        true if (tile >= threshold) == right_side => Some(tile % 32),
+                        _ => None,
+                    };
+                    if let Some(column) = self.split_column {
+                        self.split_y = (self.split_scroll as usize + line) % 240;
+                        return Some(self.exram[(self.split_y / 8) * 32 + column])
+                    }
+                    self.tile_exram = self.exram[offset];
+                },
+                3 => { // its attribute byte, with the tile's palette copied to all four quarters
+                    if let Some(column) = self.split_column {
+                        let attribute = self.exram[0x3C0 + (self.split_y / 32) * 8 + column / 4];
+                        let shift = ((self.split_y / 16) % 2) * 4 + ((column / 2) % 2) * 2;
+                        return Some(((attribute >> shift) & 0b11) * 0x55)
+                    }
+                    if self.exram_mode == 1 {
+                        return Some((self.tile_exram >> 6) * 0x55)
+                    }
+                },
+                _ => (),
+            }
+        }
+        match self.nametables[(address % 0x1000) / 0x400] {
+            2 => Some(if self.exram_mode <= 1 { self.exram[offset] } else { 0 }),
+            3 => Some(if offset < 0x3C0 { self.fill_tile } else { self.fill_color * 0x55 }),
+            _ => None,
+        }
+    }
+
+    fn write_nametable(&mut self, address: usize, value: u8) -> bool {
+        match self.nametables[(address % 0x1000) / 0x400] {
+            2 => {
+                if self.exram_mode <= 1 {
+                    self.exram[address % 0x400] = value;
+                }
+                true
+            },
+            3 => true,
+            _ => false,
+        }
+    }
+
+    fn ppu_tick(&mut self, scanline: usize, dot: usize, rendering: bool, tall_sprites: bool) {
+        self.scanline = scanline;
+        self.dot = dot;
+        self.rendering = rendering;
+        self.tall_sprites = tall_sprites;
+        if !rendering || scanline >= 240 {
+            self.in_frame = false;
+        } else if dot == 1 {
+            self.start_scanline();
+        }
+    }
+
+    // The pulses go through the same kind of DAC as the APU's. The PCM channel is linear.
+    fn expansion_audio(&mut self) -> f32 {
+        self.pulse1.clock();
+        self.pulse2.clock();
+        self.audio_timer += 1;
+        if self.audio_timer == AUDIO_FRAME_PERIOD {
+            self.audio_timer = 0;
+            for pulse in [&mut self.pulse1, &mut self.pulse2] {
+                pulse.envelope.clock();
+                pulse.clock_length_counter();
+            }
+        }
+        let pulses = (self.pulse1.sample + self.pulse2.sample) as f32;
+        let pulse_out = 95.52 / ((8128.0 / pulses) + 100.0);
+        let pcm_out = self.pcm as f32 / 255.0 * 0.4;
+        pulse_out + pcm_out
+    }
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Mmc5(Box::new(
+            Mmc5Data {
+                cart: self.cart.clone(),
+                prg_ram: self.prg_ram.clone(),
+                chr_ram: self.chr_ram.clone(),
+                exram: self.exram.clone(),
+                prg_mode: self.prg_mode,
+                chr_mode: self.chr_mode,
+                prg_ram_protect: self.prg_ram_protect,
+                exram_mode: self.exram_mode,
+                nametables: self.nametables,
+                fill_tile: self.fill_tile,
+                fill_color: self.fill_color,
+                prg_banks: self.prg_banks,
+                chr_banks: self.chr_banks,
+                chr_upper: self.chr_upper,
+                last_chr_background: self.last_chr_background,
+                split_control: self.split_control,
+                split_scroll: self.split_scroll,
+                split_bank: self.split_bank,
+                irq_compare: self.irq_compare,
+                irq_enabled: self.irq_enabled,
+                multiplicands: self.multiplicands,
+                scanline: self.scanline,
+                dot: self.dot,
+                rendering: self.rendering,
+                tall_sprites: self.tall_sprites,
+                in_frame: self.in_frame,
+                scanline_counter: self.scanline_counter,
+                irq_pending: self.irq_pending,
+                tile_exram: self.tile_exram,
+                split_column: self.split_column,
+                split_y: self.split_y,
+                pulse1: self.pulse1.clone(),
+                pulse2: self.pulse2.clone(),
+                audio_timer: self.audio_timer,
+                pcm: self.pcm,
+                pcm_read_mode: self.pcm_read_mode,
+                pcm_irq_enabled: self.pcm_irq_enabled,
+                pcm_irq: self.pcm_irq,
+            }
+        ))
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Mmc5(mmc5_data) = mapper_data {
+            self.cart = mmc5_data.cart;
+            self.prg_ram = mmc5_data.prg_ram;
+            self.chr_ram = mmc5_data.chr_ram;
+            self.exram = mmc5_data.exram;
+            self.prg_mode = mmc5_data.prg_mode;
+            self.chr_mode = mmc5_data.chr_mode;
+            self.prg_ram_protect = mmc5_data.prg_ram_protect;
+            self.exram_mode = mmc5_data.exram_mode;
+            self.nametables = mmc5_data.nametables;
+            self.fill_tile = mmc5_data.fill_tile;
+            self.fill_color = mmc5_data.fill_color;
+            self.prg_banks = mmc5_data.prg_banks;
+            self.chr_banks = mmc5_data.chr_banks;
+            self.chr_upper = mmc5_data.chr_upper;
+            self.last_chr_background = mmc5_data.last_chr_background;
+            self.split_control = mmc5_data.split_control;
+            self.split_scroll = mmc5_data.split_scroll;
+            self.split_bank = mmc5_data.split_bank;
+            self.irq_compare = mmc5_data.irq_compare;
+            self.irq_enabled = mmc5_data.irq_enabled;
+            self.multiplicands = mmc5_data.multiplicands;
+            self.scanline = mmc5_data.scanline;
+            self.dot = mmc5_data.dot;
+            self.rendering = mmc5_data.rendering;
+            self.tall_sprites = mmc5_data.tall_sprites;
+            self.in_frame = mmc5_data.in_frame;
+            self.scanline_counter = mmc5_data.scanline_counter;
+            self.irq_pending = mmc5_data.irq_pending;
+            self.tile_exram = mmc5_data.tile_exram;
+            self.split_column = mmc5_data.split_column;
+            self.split_y = mmc5_data.split_y;
+            self.pulse1 = mmc5_data.pulse1;
+            self.pulse2 = mmc5_data.pulse2;
+            self.audio_timer = mmc5_data.audio_timer;
+            self.pcm = mmc5_data.pcm;
+            self.pcm_read_mode = mmc5_data.pcm_read_mode;
+            self.pcm_irq_enabled = mmc5_data.pcm_irq_enabled;
+            self.pcm_irq = mmc5_data.pcm_irq;
+        }
+    }
+}
@@ -3,6 +3,18 @@ mod mmc1;
 mod uxrom;
 mod cnrom;
 mod mmc3;
+mod mmc2;
+mod mmc5;
+mod axrom;
+mod color_dreams;
+mod bnrom;
+mod gxrom;
+mod camerica;
+mod nina;
+pub mod archive;
+pub mod game_db;
+pub mod info;
+pub mod patch;
 pub mod serialize;
 
 use nrom::Nrom;
@@ -10,20 +22,38 @@ use mmc1::Mmc1;
 use uxrom::Uxrom;
 use cnrom::Cnrom;
 use mmc3::Mmc3;
+use mmc2::Mmc2;
+use mmc5::Mmc5;
+use axrom::Axrom;
+use color_dreams::ColorDreams;
+use bnrom::Bnrom;
+use gxrom::Gxrom;
+use camerica::Camerica;
+use nina::Nina0306;
 
 use std::cell::RefCell;
-use std::fs::File;
-use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 pub trait Mapper {
-    fn read(&self, address: usize) -> u8;
+    fn read(&self, address: usize) -> Option<u8>; // None if the cartridge doesn't respond to the address, leaving the bus open
     fn write(&mut self, address: usize, value: u8);
     fn get_mirroring(&self) -> Mirror;
     fn load_battery_backed_ram(&mut self);
     fn save_battery_backed_ram(&self);
     fn clock(&mut self);
-    fn check_irq(&mut self) -> bool;
+    fn irq_asserted(&self) -> bool; // whether the cartridge is holding the CPU's IRQ line low
+    fn reset(&mut self); // the reset button. There's no reset line on the cartridge connector, so most boards don't notice.
+    // Hooks for the few boards that need more than reads and writes. They do nothing unless a mapper overrides them.
+    fn ppu_read(&mut self, _address: usize) {} // called after each PPU read from the pattern tables, for boards that watch them
+    fn cpu_read(&mut self, _address: usize) {} // called after each CPU read from $4020-$FFFF, for registers that change when read
+    // Nametable reads and writes, for boards with their own nametable memory. A read returning None, or a write
+    // returning false, goes to the console's VRAM as get_mirroring() says.
+    fn read_nametable(&mut self, _address: usize) -> Option<u8> {None}
+    fn write_nametable(&mut self, _address: usize, _value: u8) -> bool {false}
+    // Called at the start of each PPU dot, for boards that follow along with rendering
+    fn ppu_tick(&mut self, _scanline: usize, _dot: usize, _rendering: bool, _tall_sprites: bool) {}
+    fn expansion_audio(&mut self) -> f32 {0.0} // clocked with the APU. The board's own sound, mixed in with the APU's.
     fn save_state(&self) -> serialize::MapperData;
     fn load_state(&mut self, mapper_data: serialize::MapperData);
 }
@@ -35,92 +65,435 @@ pub enum Mirror {
     Horizontal,
     Vertical,
     FourScreen,
+    Custom([u8; 4]), // which of the two pages of VRAM each nametable uses, for boards that set them one by one
 }
 
-pub fn get_mapper(filename: String) -> Rc<RefCell<dyn Mapper>> {
-    let cart = Cartridge::new(filename);
+// Why a ROM couldn't be loaded
+#[derive(Debug)]
+pub enum RomError {
+    Io(std::io::Error),
+    BadSignature, // doesn't start with "NES\x1A", so it isn't an iNES file
+    TruncatedPrg { expected: usize, found: usize }, // the file ends before the PRG-ROM the header promises
+    TruncatedChr { expected: usize, found: usize },
+    UnsupportedMapper(u16),
+    BadHeader(String),
+    Archive(String), // a .zip that couldn't be read, or has no game in it
+    Patch(String), // an IPS, UPS or BPS patch that couldn't be applied
+}
+
+impl std::fmt::Display for RomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RomError::Io(e) => write!(f, "could not read ROM file: {}", e),
+            RomError::BadSignature => write!(f, "file signature mismatch: not a valid iNES file"),
+            RomError::TruncatedPrg { expected, found } =>
+                write!(f, "ROM file is cut short: header says {} bytes of PRG-ROM, file has {}", expected, found),
+            RomError::TruncatedChr { expected, found } =>
+                write!(f, "ROM file is cut short: header says {} bytes of CHR-ROM, file has {}", expected, found),
+            RomError::UnsupportedMapper(num) => write!(f, "unimplemented mapper: {}", num),
+            RomError::BadHeader(reason) => write!(f, "bad iNES header: {}", reason),
+            RomError::Archive(reason) => write!(f, "could not read archive: {}", reason),
+            RomError::Patch(reason) => write!(f, "could not apply patch: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<std::io::Error> for RomError {
+    fn from(e: std::io::Error) -> Self {
+        RomError::Io(e)
+    }
+}
+
+// The mapper numbers get_mapper() knows
+pub fn mapper_supported(num: u16) -> bool {
+    matches!(num, 0..=5 | 7 | 9 | 10 | 11 | 34 | 66 | 71 | 79)
+}
+
+pub fn get_mapper(cart: Cartridge) -> Result<Rc<RefCell<dyn Mapper>>, RomError> {
     let num = cart.mapper_num;
-    match num {
+    let mapper: Rc<RefCell<dyn Mapper>> = match num {
         0 => Rc::new(RefCell::new(Nrom::new(cart))),
         1 => Rc::new(RefCell::new(Mmc1::new(cart))),
         2 => Rc::new(RefCell::new(Uxrom::new(cart))),
         3 => Rc::new(RefCell::new(Cnrom::new(cart))),
         4 => Rc::new(RefCell::new(Mmc3::new(cart))),
-        _ => panic!("unimplemented mapper: {}", num),
-    }
+        5 => Rc::new(RefCell::new(Mmc5::new(cart))),
+        7 => Rc::new(RefCell::new(Axrom::new(cart))),
+        9 | 10 => Rc::new(RefCell::new(Mmc2::new(cart))),
+        11 => Rc::new(RefCell::new(ColorDreams::new(cart))),
+        34 => Rc::new(RefCell::new(Bnrom::new(cart))),
+        66 => Rc::new(RefCell::new(Gxrom::new(cart))),
+        71 => Rc::new(RefCell::new(Camerica::new(cart))),
+        79 => Rc::new(RefCell::new(Nina0306::new(cart))),
+        _ => return Err(RomError::UnsupportedMapper(num)),
+    };
+    Ok(mapper)
+}
+
+// Which TV system the game was made for (NES 2.0 byte 12)
+#[derive(Copy, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Timing {
+    Ntsc,
+    Pal,
+    MultiRegion, // works on either
+    Dendy,
+}
+
+// What the cartridge plugs into (flags 7, and NES 2.0 byte 13 for the extended types)
+#[derive(Copy, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ConsoleType {
+    Nes, // NES or Famicom
+    VsSystem,
+    Playchoice10,
+    Extended(u8), // Famiclones, VT0x chips, etc. See https://wiki.nesdev.com/w/index.php/NES_2.0#Extended_Console_Type
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct Cartridge {
     filename: String,
-    prg_rom_size: usize,
-    chr_rom_size: usize,
+    nes2: bool, // header is NES 2.0 rather than iNES 1.0
+    prg_rom_size: usize, // in 16 KiB chunks
+    chr_rom_size: usize, // in 8 KiB chunks
     pub mirroring: Mirror, // 0 horizontal, 1 vertical
     battery_backed_ram: bool, // 1: Cartridge contains battery-backed PRG RAM ($6000-7FFF) or other persistent memory
     trainer_present: bool, // 1: 512-byte trainer at $7000-$71FF (stored before PRG data)
     four_screen_vram: bool, // 1: Ignore mirroring control or above mirroring bit; instead provide four-screen VRAM
-    // TODO: other iNES header flags
+
+    // RAM on the board, in bytes. NVRAM is the battery-backed kind.
+    prg_ram_size: usize,
+    prg_nvram_size: usize,
+    chr_ram_size: usize,
+    chr_nvram_size: usize,
+
+    timing: Timing,
+    console_type: ConsoleType,
+    expansion_device: u8, // NES 2.0: what's plugged into the controller ports, e.g. 1 for standard controllers
 
     pub prg_rom: Vec<Vec<u8>>, // 16 KiB chunks for CPU
     pub chr_rom: Vec<Vec<u8>>, // 8 KiB chunks for PPU
+    trainer: Vec<u8>, // 512 bytes if trainer_present, otherwise empty
 
     all_data: Vec<u8>,
-    mapper_num: u8,
+    prg_rom_bytes: usize, // ROM sizes from the header. NES 2.0 sizes don't have to be whole chunks.
+    chr_rom_bytes: usize,
+    mapper_num: u16,
+    submapper: u8, // NES 2.0: which variant of the mapper the board uses
+
+    // Hashes of the PRG-ROM followed by the CHR-ROM, which identify the game in the database
+    crc32: u32,
+    sha1: String,
+    db_overrides: Vec<String>, // header fields the database corrected, like "mirroring: Horizontal -> Vertical"
 }
 
 impl Cartridge {
-    pub fn new(filename: String) -> Self {
-        let mut f = std::fs::File::open(&filename).expect("could not open {}");
-        let mut data = vec![];
-        f.read_to_end(&mut data).unwrap();
-        assert!(data[0..4] == [0x4E, 0x45, 0x53, 0x1A], "signature mismatch, not an iNES file");
-        let mapper_num = ((data[7] >> 4) << 4) + (data[6] >> 4);
+    // Load a ROM from a file, or the first game in a .zip, with any patch next to it applied.
+    pub fn new(filename: String) -> Result<Self, RomError> {
+        Cartridge::new_patched(filename, &patch::PatchChoice::Found)
+    }
+
+    // The same, but choosing which IPS/UPS/BPS patch to apply.
+    pub fn new_patched(filename: String, patch: &patch::PatchChoice) -> Result<Self, RomError> {
+        let (data, filename) = patch::load_patched(&filename, None, patch)?;
+        Cartridge::from_bytes(data, filename)
+    }
+
+    // Parses a ROM image that's already in memory. The filename is only used to name battery-backed RAM save files,
+    // and can be empty if there's no file on disk, in which case none will be written.
+    pub fn from_bytes(data: Vec<u8>, filename: String) -> Result<Self, RomError> {
+        Cartridge::from_bytes_with_database(data, filename, game_db::bundled())
+    }
+
+    // The same, but fixing the header from another database, such as a newer copy of the whole nes20db.xml.
+    pub fn from_bytes_with_database(data: Vec<u8>, filename: String, database: &game_db::GameDatabase) -> Result<Self, RomError> {
+        if !data.starts_with(&[0x4E, 0x45, 0x53, 0x1A]) {
+            return Err(RomError::BadSignature);
+        }
+        if data.len() < 0x10 {
+            return Err(RomError::BadHeader(format!("file is only {} bytes long", data.len())));
+        }
+        // "If byte 7 AND $0C = $08, ... NES 2.0 format" (https://wiki.nesdev.com/w/index.php/NES_2.0)
+        let nes2 = data[7] & 0x0C == 0x08;
         let mut cart = Cartridge {
-            filename: filename.to_string(),
-            prg_rom_size: data[4] as usize,
-            chr_rom_size: data[5] as usize,
+            filename,
+            nes2,
+            prg_rom_size: 0,
+            chr_rom_size: 0,
             mirroring:       if data[6] & (1 << 0) == 0 {Mirror::Horizontal} else {Mirror::Vertical},
             battery_backed_ram: data[6] & (1 << 1) != 0,
             trainer_present:    data[6] & (1 << 2) != 0,
             four_screen_vram:   data[6] & (1 << 3) != 0,
+            prg_ram_size: 0,
+            prg_nvram_size: 0,
+            chr_ram_size: 0,
+            chr_nvram_size: 0,
+            timing: Timing::Ntsc,
+            console_type: console_type(data[7] & 3, data[13]),
+            expansion_device: 0,
             prg_rom: Vec::new(),
             chr_rom: Vec::new(),
-            all_data: data,
-            mapper_num: mapper_num,
+            trainer: Vec::new(),
+            all_data: vec![],
+            prg_rom_bytes: 0,
+            chr_rom_bytes: 0,
+            mapper_num: ((data[7] & 0xF0) | (data[6] >> 4)) as u16,
+            submapper: 0,
+            crc32: 0,
+            sha1: String::new(),
+            db_overrides: vec![],
+        };
+        if nes2 {
+            cart.read_nes2_header(&data)?;
+        } else {
+            cart.read_ines_header(&data);
+        }
+        if cart.prg_rom_bytes == 0 {
+            return Err(RomError::BadHeader("no PRG-ROM".to_string()));
+        }
+        cart.all_data = data;
+        cart.fill(database)?;
+        Ok(cart)
+    }
+
+    fn read_ines_header(&mut self, data: &[u8]) {
+        self.prg_rom_bytes = data[4] as usize * 0x4000;
+        self.chr_rom_bytes = data[5] as usize * 0x2000;
+        // iNES 1.0 only has the size of PRG-RAM, in 8KB units, and even that's rarely set, so assume 8KB.
+        // Games without CHR-ROM have 8KB of CHR-RAM.
+        let prg_ram = (data[8].max(1) as usize) * 0x2000;
+        if self.battery_backed_ram {
+            self.prg_nvram_size = prg_ram;
+        } else {
+            self.prg_ram_size = prg_ram;
+        }
+        if self.chr_rom_bytes == 0 {
+            self.chr_ram_size = 0x2000;
+        }
+        if data[9] & 1 != 0 {
+            self.timing = Timing::Pal;
+        }
+    }
+
+    // See https://wiki.nesdev.com/w/index.php/NES_2.0
+    fn read_nes2_header(&mut self, data: &[u8]) -> Result<(), RomError> {
+        self.mapper_num |= ((data[8] & 0x0F) as u16) << 8;
+        self.submapper = data[8] >> 4;
+        self.prg_rom_bytes = rom_size(data[4], data[9] & 0x0F, 0x4000)
+            .ok_or_else(|| RomError::BadHeader("PRG-ROM size is too big".to_string()))?;
+        self.chr_rom_bytes = rom_size(data[5], data[9] >> 4, 0x2000)
+            .ok_or_else(|| RomError::BadHeader("CHR-ROM size is too big".to_string()))?;
+        self.prg_ram_size = ram_size(data[10] & 0x0F);
+        self.prg_nvram_size = ram_size(data[10] >> 4);
+        self.chr_ram_size = ram_size(data[11] & 0x0F);
+        self.chr_nvram_size = ram_size(data[11] >> 4);
+        self.timing = match data[12] & 3 {
+            0 => Timing::Ntsc,
+            1 => Timing::Pal,
+            2 => Timing::MultiRegion,
+            _ => Timing::Dendy,
         };
-        cart.fill();
-        cart
+        self.expansion_device = data[15] & 0x3F;
+        Ok(())
     }
 
-    fn fill(&mut self) {
+    fn fill(&mut self, database: &game_db::GameDatabase) -> Result<(), RomError> {
         let prg_chunk_size: usize = 1<<14;
         let chr_chunk_size: usize = 1<<13;
         let prg_offset: usize = 0x10 + if self.trainer_present { 0x200 } else { 0 }; // header plus trainer if present
-        let chr_offset: usize = prg_offset + (self.prg_rom_size * prg_chunk_size); // chr comes after prg
-        // fill vecs with chunks
-        for i in 0..self.prg_rom_size {
-            let offset = prg_offset + (i * prg_chunk_size);
-            let chunk = self.all_data[offset..(offset + prg_chunk_size)].to_vec();
-            self.prg_rom.push(chunk.clone());
+        let prg_found = self.all_data.len().saturating_sub(prg_offset);
+        if prg_found < self.prg_rom_bytes {
+            return Err(RomError::TruncatedPrg { expected: self.prg_rom_bytes, found: prg_found });
+        }
+        if self.trainer_present {
+            self.trainer = self.all_data[0x10..prg_offset].to_vec();
+        }
+        let chr_offset: usize = prg_offset + self.prg_rom_bytes; // chr comes after prg
+        let chr_found = self.all_data.len() - chr_offset;
+        if chr_found < self.chr_rom_bytes {
+            return Err(RomError::TruncatedChr { expected: self.chr_rom_bytes, found: chr_found });
+        }
+        let rom = &self.all_data[prg_offset..chr_offset + self.chr_rom_bytes];
+        self.crc32 = crc32fast::hash(rom);
+        self.sha1 = sha1_smol::Sha1::from(rom).digest().to_string().to_uppercase();
+        if let Some(game) = database.lookup(self.crc32, &self.sha1) {
+            self.apply_database(&game);
+        }
+        self.prg_rom = chunks(&self.all_data[prg_offset..prg_offset + self.prg_rom_bytes], prg_chunk_size);
+        self.chr_rom = chunks(&self.all_data[chr_offset..chr_offset + self.chr_rom_bytes], chr_chunk_size);
+        self.prg_rom_size = self.prg_rom.len();
+        self.chr_rom_size = self.chr_rom.len();
+        self.all_data.clear();
+        Ok(())
+    }
+
+    // Replace what the header says with what the database knows about the board, and keep track of what changed.
+    // An iNES 1.0 header has no way to give the submapper, RAM sizes beyond a guess, or regions other than NTSC and PAL,
+    // so those are only reported as fixes for NES 2.0 headers. They're still taken from the database either way.
+    fn apply_database(&mut self, game: &game_db::GameInfo) {
+        let nes2 = self.nes2;
+        let mut overrides = vec![];
+        let mut check = |field: &str, expressible: bool, header: String, database: String| {
+            if expressible && header != database {
+                overrides.push(format!("{}: {} -> {}", field, header, database));
+            }
         };
-        for i in 0..self.chr_rom_size {
-            let offset = chr_offset + (i * chr_chunk_size);
-            let chunk = self.all_data[offset..offset + chr_chunk_size].to_vec();
-            self.chr_rom.push(chunk);
+        let mirroring = match game.mirroring {
+            Some('H') => Mirror::Horizontal,
+            Some('V') => Mirror::Vertical,
+            _ => self.mirroring, // mapper-controlled, so the header bit doesn't matter
         };
-        self.all_data.clear();
+        let timing = match game.region {
+            0 => Timing::Ntsc,
+            1 => Timing::Pal,
+            2 => Timing::MultiRegion,
+            _ => Timing::Dendy,
+        };
+        check("mapper", true, self.mapper_num.to_string(), game.mapper.to_string());
+        check("submapper", nes2, self.submapper.to_string(), game.submapper.to_string());
+        check("mirroring", true, format!("{:?}", self.mirroring), format!("{:?}", mirroring));
+        check("four-screen VRAM", true, self.four_screen_vram.to_string(), (game.mirroring == Some('4')).to_string());
+        check("battery", true, self.battery_backed_ram.to_string(), game.battery.to_string());
+        check("PRG-RAM", nes2, self.prg_ram_size.to_string(), game.prg_ram.to_string());
+        check("PRG-NVRAM", nes2, self.prg_nvram_size.to_string(), game.prg_nvram.to_string());
+        check("CHR-RAM", nes2, self.chr_ram_size.to_string(), game.chr_ram.to_string());
+        check("CHR-NVRAM", nes2, self.chr_nvram_size.to_string(), game.chr_nvram.to_string());
+        check("region", nes2 || game.region <= 1, format!("{:?}", self.timing), format!("{:?}", timing));
+
+        self.mapper_num = game.mapper;
+        self.submapper = game.submapper;
+        self.mirroring = mirroring;
+        self.four_screen_vram = game.mirroring == Some('4');
+        self.battery_backed_ram = game.battery;
+        self.prg_ram_size = game.prg_ram;
+        self.prg_nvram_size = game.prg_nvram;
+        self.chr_ram_size = game.chr_ram;
+        self.chr_nvram_size = game.chr_nvram;
+        self.timing = timing;
+        self.db_overrides = overrides;
+    }
+
+    // The trainer was for copier devices, which put it in RAM at $7000-$71FF before starting the game. Old hacks and
+    // translations often keep patch code there, so mappers with PRG-RAM copy it in at power-on, after any battery save.
+    pub fn load_trainer(&self, prg_ram: &mut [u8]) {
+        for (i, &byte) in self.trainer.iter().enumerate() {
+            write_ram(prg_ram, 0x1000 + i, byte);
+        }
+    }
+
+    // PRG-RAM at $6000-$7FFF, battery-backed or not. The mappers here don't tell the two apart,
+    // except that only battery-backed RAM gets saved.
+    pub fn prg_ram_bytes(&self) -> usize {
+        self.prg_ram_size + self.prg_nvram_size
+    }
+
+    // CHR-RAM, for boards without CHR-ROM. Some NES 2.0 headers leave the size out, so those get the usual 8KB.
+    pub fn chr_ram_bytes(&self) -> usize {
+        match self.chr_ram_size + self.chr_nvram_size {
+            0 if self.chr_rom_size == 0 => 0x2000,
+            n => n,
+        }
+    }
+
+    // Where battery-backed RAM for this game is kept: the ROM's path with a .sav extension.
+    pub fn save_file(&self) -> Option<PathBuf> {
+        if self.filename.is_empty() {
+            return None
+        }
+        let p = Path::new(&self.filename).parent()?;
+        let stem = Path::new(&self.filename).file_stem()?;
+        let mut save_file = p.join(stem);
+        save_file.set_extension("sav");
+        Some(save_file)
+    }
+
+    // Fills the mapper's battery-backed RAM from the save file, if the game has a battery and there's a save.
+    // A save file of a different size was made for some other board, so it's left alone instead of replacing the RAM.
+    pub fn load_battery_backed_ram(&self, ram: &mut [u8]) {
+        if !self.battery_backed_ram {
+            return
+        }
+        let save_file = match self.save_file() {
+            Some(f) => f,
+            None => return,
+        };
+        match std::fs::read(&save_file) {
+            Ok(data) if data.len() == ram.len() => {
+                println!("loading battery-backed RAM from file: {:?}", save_file);
+                ram.copy_from_slice(&data);
+            },
+            Ok(data) => println!("not loading {:?}: it's {} bytes, but the game has {} bytes of battery-backed RAM",
+                save_file, data.len(), ram.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+            Err(e) => println!("could not read {:?}: {}", save_file, e),
+        }
     }
 
+    pub fn save_battery_backed_ram(&self, ram: &[u8]) {
+        if !self.battery_backed_ram {
+            return
+        }
+        let save_file = match self.save_file() {
+            Some(f) => f,
+            None => return,
+        };
+        println!("saving battery-backed RAM to file: {:?}", save_file);
+        if let Err(e) = std::fs::write(&save_file, ram) {
+            println!("could not write {:?}: {}", save_file, e);
+        }
+    }
 }
 
-pub fn check_signature(filename: &str) -> Result<(), String> {
-    let mut f = File::open(filename).map_err(|e| e.to_string())?;
-    let mut data = [0; 4];
-    f.read_exact(&mut data).map_err(|e| e.to_string())?;
-    if data == [0x4E, 0x45, 0x53, 0x1A] {
-        Ok(())
+// NES 2.0 ROM sizes. "If the MSB nibble is $F, an exponent-multiplier notation is used": 2^E * (MM*2+1) bytes.
+// Otherwise the nibble and the LSB byte count units. None if the size doesn't fit in memory.
+fn rom_size(lsb: u8, msb: u8, unit: usize) -> Option<usize> {
+    if msb == 0x0F {
+        let exponent = (lsb >> 2) as u32;
+        let multiplier = (lsb & 3) as usize * 2 + 1;
+        1usize.checked_shl(exponent)?.checked_mul(multiplier)
     } else {
-        Err("file signature mismatch: not a valid iNES file".to_string())
+        Some((((msb as usize) << 8) | lsb as usize) * unit)
+    }
+}
+
+// NES 2.0 RAM sizes: "If the shift count is zero, there is no RAM. If the shift count is non-zero,
+// the actual size is 64 << shift count bytes."
+fn ram_size(shift: u8) -> usize {
+    match shift {
+        0 => 0,
+        n => 64 << n,
+    }
+}
+
+fn console_type(bits: u8, extended: u8) -> ConsoleType {
+    match bits {
+        0 => ConsoleType::Nes,
+        1 => ConsoleType::VsSystem,
+        2 => ConsoleType::Playchoice10,
+        _ => ConsoleType::Extended(extended & 0x0F),
+    }
+}
+
+// Cut ROM into the chunks the mappers bank with. A ROM smaller than a chunk is repeated to fill it,
+// the way a small chip shows up more than once in a bigger window.
+fn chunks(rom: &[u8], chunk_size: usize) -> Vec<Vec<u8>> {
+    rom.chunks(chunk_size)
+        .map(|chunk| chunk.iter().cycle().take(chunk_size).cloned().collect())
+        .collect()
+}
+
+// RAM that's smaller than the window it's in repeats through it, and missing RAM leaves the bus open.
+fn read_ram(ram: &[u8], offset: usize) -> Option<u8> {
+    match ram.len() {
+        0 => None,
+        len => Some(ram[offset % len]),
+    }
+}
+
+fn write_ram(ram: &mut [u8], offset: usize, value: u8) {
+    let len = ram.len();
+    if len > 0 {
+        ram[offset % len] = value;
     }
 }
 
@@ -0,0 +1,85 @@
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
+
+// American Video Entertainment's NINA-03 and NINA-06: one register in the expansion area, at addresses in
+// $4100-$5FFF where A8 is set and A13-A15 match $4000 (so $4100-$41FF, $4300-$43FF, and so on).
+// Bit 3 selects a 32 KB PRG bank and bits 0-2 an 8 KB CHR bank. Mirroring is fixed by the board.
+// See https://wiki.nesdev.com/w/index.php/NINA-003-006
+pub struct Nina0306 {
+    cart: Cartridge,
+    chr_ram: Vec<u8>,
+    prg_bank: usize,
+    chr_bank: usize,
+}
+
+impl Nina0306 {
+    pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        Nina0306{
+            cart,
+            chr_ram,
+            prg_bank: 0,
+            chr_bank: 0,
+        }
+    }
+}
+
+impl Mapper for Nina0306 {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
+            0x0000..=0x1FFF => {
+                if self.cart.chr_rom_size > 0 {
+                    self.cart.chr_rom[self.chr_bank % self.cart.chr_rom_size][address]
+                } else {
+                    return read_ram(&self.chr_ram, address)
+                }
+            },
+            0x8000..=0xFFFF => {
+                let chunk = (self.prg_bank * 2 + (address - 0x8000) / 0x4000) % self.cart.prg_rom_size;
+                self.cart.prg_rom[chunk][address % 0x4000]
+            },
+            _ => return None,
+        };
+        Some(val)
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        match address {
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x4020..=0x5FFF if address & 0xE100 == 0x4100 => {
+                self.prg_bank = ((value >> 3) & 1) as usize;
+                self.chr_bank = (value & 0b111) as usize;
+            },
+            _ => (), // no RAM or other registers
+        }
+    }
+
+    fn get_mirroring(&self) -> Mirror {
+        self.cart.mirroring
+    }
+
+    fn load_battery_backed_ram(&mut self) {}
+    fn save_battery_backed_ram(&self) {}
+    fn clock(&mut self) {}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
+
+    fn save_state(&self) -> MapperData {
+        MapperData::Nina0306(
+            Nina0306Data {
+                cart: self.cart.clone(),
+                chr_ram: self.chr_ram.clone(),
+                prg_bank: self.prg_bank,
+                chr_bank: self.chr_bank,
+            }
+        )
+    }
+
+    fn load_state(&mut self, mapper_data: MapperData) {
+        if let MapperData::Nina0306(nina_data) = mapper_data {
+            self.cart = nina_data.cart;
+            self.chr_ram = nina_data.chr_ram;
+            self.prg_bank = nina_data.prg_bank;
+            self.chr_bank = nina_data.chr_bank;
+        }
+    }
+}
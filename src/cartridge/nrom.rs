@@ -1,51 +1,52 @@
-use super::{Cartridge, Mapper, Mirror, serialize::*};
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
 
 pub struct Nrom {
     cart: Cartridge,
     chr_ram: Vec<u8>,
+    prg_ram: Vec<u8>,
 }
 
 impl Nrom {
     pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        let mut prg_ram = vec![0; cart.prg_ram_bytes()];
+        cart.load_trainer(&mut prg_ram);
         Nrom{
-            cart: cart,
-            chr_ram: vec![0; 0x2000],
+            cart,
+            chr_ram,
+            prg_ram,
         }
     }
 }
 
 impl Mapper for Nrom {
-    fn read(&self, address: usize) -> u8 {
+    fn read(&self, address: usize) -> Option<u8> {
         let addr = address % 0x4000;
-        match address {
+        let val = match address {
             0x0000..=0x1FFF => {
                 if self.cart.chr_rom_size > 0 {
                     self.cart.chr_rom[0][address]
                 } else {
-                    self.chr_ram[address]
+                    return read_ram(&self.chr_ram, address)
                 }
             },
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram, address % 0x2000),
             0x8000..=0xBFFF => {
                 self.cart.prg_rom[0][addr]
             },
             0xC000..=0xFFFF => {
                 self.cart.prg_rom[self.cart.prg_rom_size - 1][addr]
             },
-            _ => {println!("bad address read from NROM mapper: 0x{:X}", address); 0},
-        }
+            _ => return None,
+        };
+        Some(val)
     }
 
     fn write(&mut self, address: usize, value: u8) {
         match address {
-            0x0000..=0x1FFF => {
-                // ROM isn't written to
-                if self.cart.chr_rom_size == 0 {
-                    self.chr_ram[address] = value;
-                }
-            },
-            0x8000..=0xBFFF => (),
-            0xC000..=0xFFFF => (),
-            _ => println!("bad address written to NROM mapper: 0x{:X}", address),
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x6000..=0x7FFF => write_ram(&mut self.prg_ram, address % 0x2000, value),
+            _ => (), // ROM isn't written to
         }
     }
 
@@ -56,13 +57,15 @@ impl Mapper for Nrom {
     fn load_battery_backed_ram(&mut self) {}
     fn save_battery_backed_ram(&self) {}
     fn clock(&mut self) {}
-    fn check_irq(&mut self) -> bool {false}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
 
     fn save_state(&self) -> MapperData {
         MapperData::Nrom(
             NromData {
                 cart: self.cart.clone(),
                 chr_ram: self.chr_ram.clone(),
+                prg_ram: self.prg_ram.clone(),
             }
         )
     }
@@ -71,6 +74,7 @@ impl Mapper for Nrom {
         if let MapperData::Nrom(nrom_data) = mapper_data {
             self.cart = nrom_data.cart;
             self.chr_ram = nrom_data.chr_ram;
+            self.prg_ram = nrom_data.prg_ram;
         }
     }
 }
@@ -0,0 +1,260 @@
+// Soft-patching: applying a translation or hack to a ROM as it's loaded, leaving the file on disk alone.
+// Three formats are common:
+//     IPS: "PATCH", then records of a 3-byte offset, 2-byte size and that many bytes to write there,
+//          or size 0, a 2-byte count and a byte to repeat. Ends with "EOF". No checksums.
+//     UPS: "UPS1", the file sizes, then runs of bytes to XOR in at offsets from the end of the last run.
+//     BPS: "BPS1", the file sizes and metadata, then commands that build the new file out of pieces of the old one,
+//          of itself, or of bytes in the patch.
+// UPS and BPS end with CRC32s of the original file, the patched file and the patch, so a patch for a different dump
+// of the game is caught instead of producing garbage.
+// See https://zerosoft.zophar.net/ips.php, and byuu's specs for UPS and BPS.
+
+use super::RomError;
+use super::archive::{read_rom_file, MAX_ROM_SIZE};
+
+use std::convert::TryFrom;
+use std::path::{Path, PathBuf};
+
+const PATCH_EXTENSIONS: [&str; 3] = ["ips", "bps", "ups"];
+
+// Which patch to apply to a ROM as it's loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchChoice {
+    Found,        // one next to the ROM with the same name, like `game.ips` for `game.nes`, if there is one
+    File(String), // this one
+    Skip,         // none, even if there's one next to the ROM
+}
+
+// Reads a ROM (see archive::read_rom_file) and applies a patch to it. Returns the image and the filename saves should be
+// named after, which for a patched game is the patch's with the ROM's extension added, `game.ips.nes` for `game.ips`,
+// so the hack's saves don't mix with the original game's even when the patch has the same name as the ROM.
+pub fn load_patched(path: &str, entry: Option<&str>, patch: &PatchChoice) -> Result<(Vec<u8>, String), RomError> {
+    let (rom, filename) = read_rom_file(path, entry)?;
+    let patch_file = match patch {
+        PatchChoice::File(p) => PathBuf::from(p),
+        PatchChoice::Found => match find_patch(&filename) {
+            Some(p) => p,
+            None => return Ok((rom, filename)),
+        },
+        PatchChoice::Skip => return Ok((rom, filename)),
+    };
+    let patch_data = std::fs::read(&patch_file)?;
+    let patched = apply_patch(&rom, &patch_data)?;
+    let extension = Path::new(&filename).extension().and_then(|e| e.to_str()).unwrap_or("nes");
+    Ok((patched, format!("{}.{}", patch_file.to_string_lossy(), extension)))
+}
+
+pub fn find_patch(filename: &str) -> Option<PathBuf> {
+    PATCH_EXTENSIONS.iter()
+        .map(|ext| Path::new(filename).with_extension(ext))
+        .find(|p| p.exists())
+}
+
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    if patch.starts_with(b"PATCH") {
+        apply_ips(rom, patch)
+    } else if patch.starts_with(b"UPS1") {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else {
+        Err(RomError::Patch("not an IPS, UPS or BPS patch".to_string()))
+    }
+}
+
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    let mut output = rom.to_vec();
+    let mut reader = PatchReader { patch, position: 5 };
+    loop {
+        let offset_bytes = reader.bytes(3)?;
+        if offset_bytes == b"EOF" {
+            break;
+        }
+        let offset = big_endian(offset_bytes);
+        let size = big_endian(reader.bytes(2)?);
+        let data = match size {
+            0 => { // run-length encoded
+                let count = big_endian(reader.bytes(2)?);
+                vec![reader.byte()?; count]
+            },
+            _ => reader.bytes(size)?.to_vec(),
+        };
+        if output.len() < offset + data.len() {
+            output.resize(offset + data.len(), 0);
+        }
+        output[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    // an extension some patches use to shrink the file
+    if let Ok(size) = reader.bytes(3) {
+        output.truncate(big_endian(size));
+    }
+    Ok(output)
+}
+
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    let (source_crc, target_crc) = check_patch_crcs(rom, patch)?;
+    let mut reader = PatchReader { patch, position: 4 };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    if source_size != rom.len() {
+        return Err(wrong_rom(source_crc));
+    }
+    check_target_size(target_size)?;
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+    let mut position: usize = 0;
+    while reader.position < patch.len() - 12 {
+        position = position.checked_add(reader.number()?).ok_or_else(too_big)?;
+        loop {
+            let x = reader.byte()?;
+            if position < target_size {
+                output[position] ^= x;
+            }
+            position += 1;
+            if x == 0 {
+                break;
+            }
+        }
+    }
+    check_output(&output, target_crc)?;
+    Ok(output)
+}
+
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, RomError> {
+    let (source_crc, target_crc) = check_patch_crcs(rom, patch)?;
+    let mut reader = PatchReader { patch, position: 4 };
+    let source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.bytes(metadata_size)?;
+    if source_size != rom.len() {
+        return Err(wrong_rom(source_crc));
+    }
+    check_target_size(target_size)?;
+    let corrupt = || RomError::Patch("BPS patch copies from outside the file".to_string());
+    let mut output = Vec::with_capacity(target_size);
+    let mut source_offset: isize = 0;
+    let mut target_offset: isize = 0;
+    while reader.position < patch.len() - 12 {
+        let command = reader.number()?;
+        let length = (command >> 2) + 1;
+        if length > target_size - output.len() {
+            return Err(RomError::Patch(format!("BPS patch makes more than {} bytes", target_size)));
+        }
+        match command & 3 {
+            0 => { // SourceRead: the same bytes as the original at this position
+                let start = output.len();
+                output.extend_from_slice(slice(rom, start, length).ok_or_else(corrupt)?);
+            },
+            1 => output.extend_from_slice(reader.bytes(length)?), // TargetRead: new bytes from the patch
+            2 => { // SourceCopy: bytes from elsewhere in the original
+                source_offset = source_offset.checked_add(reader.signed_number()?).ok_or_else(corrupt)?;
+                let start = usize::try_from(source_offset).map_err(|_| corrupt())?;
+                output.extend_from_slice(slice(rom, start, length).ok_or_else(corrupt)?);
+                source_offset += length as isize;
+            },
+            _ => { // TargetCopy: bytes from earlier in the output, one at a time since the copy can overlap itself
+                target_offset = target_offset.checked_add(reader.signed_number()?).ok_or_else(corrupt)?;
+                for _ in 0..length {
+                    let byte = *usize::try_from(target_offset).ok().and_then(|t| output.get(t)).ok_or_else(corrupt)?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            },
+        }
+    }
+    if output.len() != target_size {
+        return Err(RomError::Patch(format!("BPS patch made {} bytes instead of {}", output.len(), target_size)));
+    }
+    check_output(&output, target_crc)?;
+    Ok(output)
+}
+
+// Checks the patch's own CRC and the original file's, and returns the CRCs the original and patched files should have.
+fn check_patch_crcs(rom: &[u8], patch: &[u8]) -> Result<(u32, u32), RomError> {
+    if patch.len() < 4 + 12 {
+        return Err(RomError::Patch("patch is too short".to_string()));
+    }
+    let crc = |offset: usize| u32::from_le_bytes([patch[offset], patch[offset + 1], patch[offset + 2], patch[offset + 3]]);
+    let end = patch.len();
+    let (source_crc, target_crc, patch_crc) = (crc(end - 12), crc(end - 8), crc(end - 4));
+    if crc32fast::hash(&patch[..end - 4]) != patch_crc {
+        return Err(RomError::Patch("patch file is corrupt (checksum mismatch)".to_string()));
+    }
+    if crc32fast::hash(rom) != source_crc {
+        return Err(wrong_rom(source_crc));
+    }
+    Ok((source_crc, target_crc))
+}
+
+// The sizes in a patch are checked before anything is allocated for them, since a corrupt one could ask for anything.
+fn check_target_size(target_size: usize) -> Result<(), RomError> {
+    match target_size {
+        n if n > MAX_ROM_SIZE => Err(RomError::Patch(format!("patch makes a {} byte file, more than {} MiB", n, MAX_ROM_SIZE >> 20))),
+        _ => Ok(()),
+    }
+}
+
+fn check_output(output: &[u8], target_crc: u32) -> Result<(), RomError> {
+    match crc32fast::hash(output) {
+        crc if crc == target_crc => Ok(()),
+        crc => Err(RomError::Patch(format!("patched ROM has CRC32 {:08X}, expected {:08X}", crc, target_crc))),
+    }
+}
+
+fn big_endian(bytes: &[u8]) -> usize {
+    bytes.iter().fold(0, |n, &b| (n << 8) | b as usize)
+}
+
+fn slice(data: &[u8], start: usize, length: usize) -> Option<&[u8]> {
+    data.get(start..start.checked_add(length)?)
+}
+
+fn too_big() -> RomError {
+    RomError::Patch("number in patch is too big".to_string())
+}
+
+fn wrong_rom(expected_crc: u32) -> RomError {
+    RomError::Patch(format!("patch is for a different ROM (one with CRC32 {:08X})", expected_crc))
+}
+
+struct PatchReader<'a> {
+    patch: &'a [u8],
+    position: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], RomError> {
+        let bytes = slice(self.patch, self.position, count)
+            .ok_or_else(|| RomError::Patch("patch ends too soon".to_string()))?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, RomError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    // UPS and BPS numbers: 7 bits per byte, low bits first, with the top bit marking the last byte.
+    // Each byte after the first also adds one step, so there's only one way to encode each number.
+    fn number(&mut self) -> Result<usize, RomError> {
+        let mut value: usize = 0;
+        let mut shift: usize = 1;
+        loop {
+            let x = self.byte()?;
+            value = ((x & 0x7F) as usize).checked_mul(shift).and_then(|n| value.checked_add(n)).ok_or_else(too_big)?;
+            if x & 0x80 != 0 {
+                return Ok(value);
+            }
+            shift = shift.checked_mul(0x80).ok_or_else(too_big)?;
+            value = value.checked_add(shift).ok_or_else(too_big)?;
+        }
+    }
+
+    // BPS copy offsets are relative, with the sign in the low bit.
+    fn signed_number(&mut self) -> Result<isize, RomError> {
+        let n = self.number()?;
+        let magnitude = (n >> 1) as isize;
+        Ok(if n & 1 == 1 { -magnitude } else { magnitude })
+    }
+}
@@ -1,4 +1,5 @@
 use super::{Cartridge, Mirror};
+use crate::apu::square::Square;
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub enum MapperData {
@@ -7,6 +8,14 @@ pub enum MapperData {
     Uxrom(UxromData),
     Cnrom(CnromData),
     Mmc3(Mmc3Data),
+    Mmc2(Mmc2Data),
+    Mmc5(Box<Mmc5Data>), // boxed since it's so much bigger than the others
+    Axrom(AxromData),
+    ColorDreams(ColorDreamsData),
+    Bnrom(BnromData),
+    Gxrom(GxromData),
+    Camerica(CamericaData),
+    Nina0306(Nina0306Data),
 }
 
 
@@ -14,6 +23,7 @@ pub enum MapperData {
 pub struct NromData {
     pub cart: Cartridge,
     pub chr_ram: Vec<u8>,
+    pub prg_ram: Vec<u8>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
@@ -37,12 +47,14 @@ pub struct Mmc1Data {
 pub struct UxromData {
     pub cart: Cartridge,
     pub chr_ram: Vec<u8>,
+    pub prg_ram: Vec<u8>,
     pub bank_select: usize,
 }
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct CnromData {
     pub cart: Cartridge,
+    pub prg_ram: Vec<u8>,
     pub chr_bank_select: usize,
 }
 
@@ -57,9 +69,112 @@ pub struct Mmc3Data {
     pub irq_enable: bool,
     pub trigger_irq: bool,
     pub reload_counter: bool,
-    pub irq_delay: u8,
     pub prg_ram_bank: Vec<u8>,
     pub prg_rom_bank_mode: bool,
     pub chr_rom_bank_mode: bool,
     pub chr_ram_bank: Vec<u8>, 
 }
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct AxromData {
+    pub cart: Cartridge,
+    pub chr_ram: Vec<u8>,
+    pub prg_bank: usize,
+    pub mirroring: Mirror,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ColorDreamsData {
+    pub cart: Cartridge,
+    pub chr_ram: Vec<u8>,
+    pub prg_bank: usize,
+    pub chr_bank: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BnromData {
+    pub cart: Cartridge,
+    pub nina_001: bool,
+    pub chr_ram: Vec<u8>,
+    pub prg_ram: Vec<u8>,
+    pub prg_bank: usize,
+    pub chr_banks: [usize; 2],
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct GxromData {
+    pub cart: Cartridge,
+    pub chr_ram: Vec<u8>,
+    pub prg_bank: usize,
+    pub chr_bank: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct CamericaData {
+    pub cart: Cartridge,
+    pub chr_ram: Vec<u8>,
+    pub prg_bank: usize,
+    pub mirroring: Option<Mirror>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Nina0306Data {
+    pub cart: Cartridge,
+    pub chr_ram: Vec<u8>,
+    pub prg_bank: usize,
+    pub chr_bank: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Mmc2Data {
+    pub cart: Cartridge,
+    pub mmc4: bool,
+    pub mirroring: Mirror,
+    pub prg_bank: usize,
+    pub chr_banks: [[usize; 2]; 2],
+    pub latches: [usize; 2],
+    pub prg_ram_bank: Vec<u8>,
+    pub chr_ram_bank: Vec<u8>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Mmc5Data {
+    pub cart: Cartridge,
+    pub prg_ram: Vec<u8>,
+    pub chr_ram: Vec<u8>,
+    pub exram: Vec<u8>,
+    pub prg_mode: u8,
+    pub chr_mode: u8,
+    pub prg_ram_protect: [u8; 2],
+    pub exram_mode: u8,
+    pub nametables: [u8; 4],
+    pub fill_tile: u8,
+    pub fill_color: u8,
+    pub prg_banks: [u8; 5],
+    pub chr_banks: [usize; 12],
+    pub chr_upper: usize,
+    pub last_chr_background: bool,
+    pub split_control: u8,
+    pub split_scroll: u8,
+    pub split_bank: usize,
+    pub irq_compare: u8,
+    pub irq_enabled: bool,
+    pub multiplicands: [u8; 2],
+    pub scanline: usize,
+    pub dot: usize,
+    pub rendering: bool,
+    pub tall_sprites: bool,
+    pub in_frame: bool,
+    pub scanline_counter: u8,
+    pub irq_pending: bool,
+    pub tile_exram: u8,
+    pub split_column: Option<usize>,
+    pub split_y: usize,
+    pub pulse1: Square,
+    pub pulse2: Square,
+    pub audio_timer: usize,
+    pub pcm: u8,
+    pub pcm_read_mode: bool,
+    pub pcm_irq_enabled: bool,
+    pub pcm_irq: bool,
+}
@@ -1,46 +1,50 @@
-use super::{Cartridge, Mapper, Mirror, serialize::*};
+use super::{Cartridge, Mapper, Mirror, serialize::*, read_ram, write_ram};
 
 pub struct Uxrom {
     cart: Cartridge,
     chr_ram: Vec<u8>,
+    prg_ram: Vec<u8>,
     bank_select: usize,
 }
 
 impl Uxrom {
     pub fn new(cart: Cartridge) -> Self {
+        let chr_ram = vec![0; cart.chr_ram_bytes()];
+        let mut prg_ram = vec![0; cart.prg_ram_bytes()];
+        cart.load_trainer(&mut prg_ram);
         Uxrom{
-            cart: cart,
-            chr_ram: vec![0; 0x2000],
+            cart,
+            chr_ram,
+            prg_ram,
             bank_select: 0,
         }
     }
 }
 
 impl Mapper for Uxrom {
-    fn read(&self, address: usize) -> u8 {
-        match address {
+    fn read(&self, address: usize) -> Option<u8> {
+        let val = match address {
             0x0000..=0x1FFF => {
                 if self.cart.chr_rom_size > 0 {
                     self.cart.chr_rom[0][address]
                 } else {
-                    self.chr_ram[address]
+                    return read_ram(&self.chr_ram, address)
                 }
             },
+            0x6000..=0x7FFF => return read_ram(&self.prg_ram, address % 0x2000),
             0x8000..=0xBFFF => self.cart.prg_rom[self.bank_select][address % 0x4000],
             0xC000..=0xFFFF => self.cart.prg_rom[self.cart.prg_rom.len()-1][address % 0x4000],
-            _ => {println!("bad address read from UxROM mapper: 0x{:X}", address); 0},
-        }
+            _ => return None,
+        };
+        Some(val)
     }
 
     fn write(&mut self, address: usize, value: u8) {
         match address {
-            0x0000..=0x1FFF => {
-                if self.cart.chr_rom_size == 0 {
-                    self.chr_ram[address] = value;
-                }
-            },
+            0x0000..=0x1FFF if self.cart.chr_rom_size == 0 => write_ram(&mut self.chr_ram, address, value),
+            0x6000..=0x7FFF => write_ram(&mut self.prg_ram, address % 0x2000, value),
             0x8000..=0xFFFF => self.bank_select = value as usize,
-            _ => println!("bad address written to UxROM mapper: 0x{:X}", address),
+            _ => (),
         }
     }
 
@@ -51,13 +55,15 @@ impl Mapper for Uxrom {
     fn load_battery_backed_ram(&mut self) {}
     fn save_battery_backed_ram(&self) {}
     fn clock(&mut self) {}
-    fn check_irq(&mut self) -> bool {false}
+    fn irq_asserted(&self) -> bool {false}
+    fn reset(&mut self) {}
 
     fn save_state(&self) -> MapperData {
         MapperData::Uxrom(
             UxromData {
                 cart: self.cart.clone(),
                 chr_ram: self.chr_ram.clone(),
+                prg_ram: self.prg_ram.clone(),
                 bank_select: self.bank_select,
             }
         )
@@ -67,6 +73,7 @@ impl Mapper for Uxrom {
         if let MapperData::Uxrom(uxrom_data) = mapper_data {
             self.cart = uxrom_data.cart;
             self.chr_ram = uxrom_data.chr_ram;
+            self.prg_ram = uxrom_data.prg_ram;
             self.bank_select = uxrom_data.bank_select;
         }
     }
@@ -0,0 +1,163 @@
+use crate::{Cpu, Ppu, Apu};
+use crate::cartridge::{get_mapper, Cartridge, RomError};
+use crate::cartridge::patch::{load_patched, PatchChoice};
+use crate::nes_bus::NesBus;
+use crate::power_on::PowerOnOptions;
+use crate::state::SaveState;
+
+pub const SCREEN_WIDTH: usize = 256;
+pub const SCREEN_HEIGHT: usize = 240;
+
+// One frame of video: raw RGB data for the screen, 3 bytes per pixel, row by row.
+pub struct Frame {
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Frame {
+            pixels: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT * 3],
+        }
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        let offset = (y * 3 * SCREEN_WIDTH) + (x * 3);
+        self.pixels[offset..offset+3].copy_from_slice(&color);
+    }
+}
+
+// The whole console with no frontend attached: feed it controller input a frame at a time
+// and take the video and audio it produces. The SDL binary is one user of this.
+pub struct Console {
+    pub cpu: Cpu<NesBus>,
+    rom: Vec<u8>,       // raw ROM image, kept so the console can be power cycled
+    filename: String,   // empty if the ROM didn't come from a file
+    power_on: PowerOnOptions,
+    button_states: u8,  // Player 1 controller, applied whenever the game strobes it
+}
+
+impl Console {
+    // Load a ROM from a file, or the first game in a .zip, with any IPS/UPS/BPS patch next to it applied.
+    // Battery-backed RAM will be read from and saved next to it.
+    pub fn new(filename: &str) -> Result<Self, RomError> {
+        Console::new_with_options(filename, PowerOnOptions::new())
+    }
+
+    // Load a ROM that's already in memory. Battery-backed RAM won't be persisted.
+    pub fn load_rom(rom: &[u8]) -> Result<Self, RomError> {
+        Console::load_rom_with_options(rom, PowerOnOptions::new())
+    }
+
+    // The same, but choosing what the console looks like when it's turned on. The options also apply to power_cycle().
+    pub fn new_with_options(filename: &str, power_on: PowerOnOptions) -> Result<Self, RomError> {
+        Console::open(filename, None, &PatchChoice::Found, power_on)
+    }
+
+    // Load a particular game from a .zip (see archive::rom_entries), or the first one if entry is None, and apply
+    // a patch (see patch::load_patched). Entry is ignored for plain ROM files.
+    pub fn open(filename: &str, entry: Option<&str>, patch: &PatchChoice, power_on: PowerOnOptions) -> Result<Self, RomError> {
+        let (rom, game_filename) = load_patched(filename, entry, patch)?;
+        Console::build(rom, game_filename, power_on)
+    }
+
+    pub fn load_rom_with_options(rom: &[u8], power_on: PowerOnOptions) -> Result<Self, RomError> {
+        Console::build(rom.to_vec(), String::new(), power_on)
+    }
+
+    fn build(rom: Vec<u8>, filename: String, power_on: PowerOnOptions) -> Result<Self, RomError> {
+        let cpu = Console::power_on(&rom, &filename, &power_on)?;
+        Ok(Console {
+            cpu,
+            rom,
+            filename,
+            power_on,
+            button_states: 0,
+        })
+    }
+
+    fn power_on(rom: &[u8], filename: &str, options: &PowerOnOptions) -> Result<Cpu<NesBus>, RomError> {
+        let cart = Cartridge::from_bytes(rom.to_vec(), filename.to_string())?;
+        let mapper = get_mapper(cart)?;
+        let mut ppu = Ppu::new(mapper.clone());
+        ppu.warmup = options.ppu_warmup;
+        ppu.warming_up = options.ppu_warmup;
+        for _ in 0..options.ppu_offset % 3 {
+            ppu.clock();
+        }
+        let apu = Apu::new();
+        Ok(Cpu::new(NesBus::new(mapper, ppu, apu, options.ram.fill(0x800))))
+    }
+
+    // Run the console until the PPU finishes the next frame, with the given controller input.
+    // Bits of button_states, from 0 to 7: A, B, Select, Start, Up, Down, Left, Right.
+    pub fn step_frame(&mut self, button_states: u8) -> &Frame {
+        self.button_states = button_states;
+        while !self.step() {}
+        &self.cpu.bus.frame
+    }
+
+    // Perform one CPU instruction, which runs the APU and PPU alongside it cycle by cycle.
+    // Returns true if the PPU finished a frame during that time.
+    pub fn step(&mut self) -> bool {
+        self.cpu.bus.frame_done = false;
+        self.cpu.step();
+        // the controller only takes new button states while the game is strobing it
+        if self.cpu.bus.strobe & 1 == 1 {
+            self.cpu.bus.button_states = self.button_states;
+        }
+        self.cpu.bus.frame_done
+    }
+
+    // The file the game came from, which saves are named after. For a game from a .zip, its name from inside the archive.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    // The most recently completed frame.
+    pub fn frame(&self) -> &Frame {
+        &self.cpu.bus.frame
+    }
+
+    // Raw APU output since the last call, one sample per APU cycle (894,886.5 Hz).
+    pub fn audio_samples(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.cpu.bus.audio_buffer)
+    }
+
+    // Press the reset button. RAM, PRG-RAM and mapper registers survive, so games can tell this from a power cycle.
+    pub fn reset(&mut self) {
+        self.cpu.bus.mapper.borrow_mut().reset();
+        self.cpu.bus.ppu.reset();
+        self.cpu.bus.apu.reset();
+        self.cpu.reset();
+    }
+
+    // Start the game over from scratch, as if the console had been turned off and on.
+    // Battery-backed RAM is saved first, so it's still there afterwards.
+    pub fn power_cycle(&mut self) {
+        self.save_battery_backed_ram();
+        // the same ROM already loaded once, when the console was built
+        self.cpu = Console::power_on(&self.rom, &self.filename, &self.power_on).expect("ROM stopped loading");
+    }
+
+    pub fn snapshot(&self) -> SaveState {
+        SaveState {
+            cpu: self.cpu.save_state(),
+            bus: self.cpu.bus.save_state(),
+            ppu: self.cpu.bus.ppu.save_state(),
+            apu: self.cpu.bus.apu.save_state(),
+            mapper: self.cpu.bus.mapper.borrow().save_state(),
+        }
+    }
+
+    pub fn restore(&mut self, state: SaveState) {
+        self.cpu.load_state(state.cpu);
+        self.cpu.bus.load_state(state.bus);
+        self.cpu.bus.ppu.load_state(state.ppu);
+        self.cpu.bus.apu.load_state(state.apu);
+        self.cpu.bus.mapper.borrow_mut().load_state(state.mapper);
+    }
+
+    pub fn save_battery_backed_ram(&self) {
+        self.cpu.bus.mapper.borrow().save_battery_backed_ram();
+    }
+}
@@ -1,59 +1,43 @@
-impl super::Cpu {
+use super::bus::Bus;
+
+impl<B: Bus> super::Cpu<B> {
+
+    // Each read here is one cycle on the bus. The instruction then does its own reads and writes of the operand,
+    // except for implied and accumulator instructions, whose second cycle is a dummy read of the next byte.
 
     pub fn absolute(&mut self) -> usize {
-        self.clock += 4;
-        <usize>::from(
-            ((self.read(self.pc + 2) as usize) << 8) + // high byte, little endian
-            (self.read(self.pc + 1)) as usize // low byte
-        )
+        let low_byte = self.read(self.pc_plus(1)) as usize;
+        let high_byte = self.read(self.pc_plus(2)) as usize;
+        (high_byte << 8) + low_byte // little endian
     }
 
     pub fn absolute_x(&mut self) -> usize {
-        let current_opcode = self.read(self.pc);
         let old_address = self.absolute();
-        let new_address = old_address + self.x as usize;
-        match current_opcode {
-            0x1C | 0x1D | 0x3C | 0x3D | 0x5C | 0x5D | 0x7C | 0x7D | 0xBC | 0xBD | 0xDC | 0xDD | 0xFC | 0xFD
-                => self.address_page_cross(old_address, new_address),
-            0x1E | 0x1F | 0x3E | 0x3F | 0x5E | 0x5F | 0x7E | 0x7F | 0x9D | 0xC3 | 0xC7 | 0xCF | 0xD3 | 0xD7 | 0xDB | 0xDE | 0xDF | 0xFE | 0xFF
-                => self.clock += 1,
-            _ => panic!("illegal opcode using abs x: {:02x}", current_opcode),
-        }
-        new_address
+        self.add_index(old_address, self.x)
     }
 
     pub fn absolute_y(&mut self) -> usize {
-        let current_opcode = self.pc;
-        let old_address = self.absolute() as u16; // coerce to u16 for wrapping addition
-        let new_address = old_address.wrapping_add(self.y as u16);
-        let old_address = old_address as usize; // coerce back
-        let new_address = new_address as usize;
-        if current_opcode == 0x99 {
-            self.clock += 1;
-        } else {
-            self.address_page_cross(old_address, new_address);
-        }
-        new_address
+        let old_address = self.absolute();
+        self.add_index(old_address, self.y)
     }
 
     pub fn accumulator(&mut self) -> usize {
-        self.clock += 2;
+        self.read(self.pc_plus(1));
         0
     }
 
     pub fn immediate(&mut self) -> usize {
-        self.clock += 2;
-        self.pc + 1
+        self.pc_plus(1)
     }
 
     pub fn implied(&mut self) -> usize {
-        self.clock += 2;
+        self.read(self.pc_plus(1));
         0
     }
 
     pub fn indexed_indirect(&mut self) -> usize {
-        self.clock += 6;
-        let operand = self.read(self.pc + 1);
+        let operand = self.read(self.pc_plus(1));
+        self.read(operand as usize); // dummy read while X is added
         let zp_low_addr = operand.wrapping_add(self.x);
         let zp_high_addr = zp_low_addr.wrapping_add(1); // take account of zero page wraparound
         let zp_low_byte = self.read(zp_low_addr as usize);
@@ -62,61 +46,72 @@ impl super::Cpu {
     }
 
     pub fn indirect(&mut self) -> usize {
-        let operand_address = ((self.read(self.pc + 2) as usize) << 8)
-            + (self.read(self.pc + 1) as usize);
+        let operand_address = self.absolute();
         let low_byte = self.read(operand_address) as usize;
         // BUG TIME! from https://wiki.nesdev.com/w/index.php/Errata
         // "JMP ($xxyy), or JMP indirect, does not advance pages if the lower eight bits
         // of the specified address is $FF; the upper eight bits are fetched from $xx00,
         // 255 bytes earlier, instead of the expected following byte."
         let high_byte = if operand_address & 0xFF == 0xFF {
-            (self.read(operand_address as usize - 0xFF) as usize) << 8
+            (self.read(operand_address - 0xFF) as usize) << 8
         } else {
-            (self.read(operand_address as usize + 1) as usize) << 8
+            (self.read(operand_address + 1) as usize) << 8
         };
-        let real_address = high_byte + low_byte;
-        self.clock += 5;
-        real_address
+        high_byte + low_byte
     }
 
     pub fn indirect_indexed(&mut self) -> usize {
-        let operand = self.read(self.pc + 1);
+        let operand = self.read(self.pc_plus(1));
         let zp_low_addr = operand;
         let zp_high_addr = operand.wrapping_add(1);
         let zp_low_byte = self.read(zp_low_addr as usize);
         let zp_high_byte = self.read(zp_high_addr as usize);
-        let old_address = ((zp_high_byte as u16) << 8) + zp_low_byte as u16;
-        let new_address = old_address.wrapping_add(self.y as u16);
-        if self.pc == 0xF1 {
-            self.clock += 1;
-        } else {
-            self.address_page_cross(old_address as usize, new_address as usize);
-        }
-        self.clock += 5;
-        new_address as usize
+        let old_address = ((zp_high_byte as usize) << 8) + zp_low_byte as usize;
+        self.add_index(old_address, self.y)
     }
 
     pub fn relative(&mut self) -> usize {
-        self.clock += 2;
-        self.pc + 1
+        self.pc_plus(1)
     }
 
     pub fn zero_page(&mut self) -> usize {
-        let operand = self.read(self.pc + 1);
-        self.clock += 3;
+        let operand = self.read(self.pc_plus(1));
         operand as usize
     }
 
     pub fn zero_page_x(&mut self) -> usize {
-        let operand = self.read(self.pc + 1);
-        self.clock += 4;
+        let operand = self.read(self.pc_plus(1));
+        self.read(operand as usize); // dummy read while X is added
         operand.wrapping_add(self.x) as usize
     }
 
     pub fn zero_page_y(&mut self) -> usize {
-        let operand = self.read(self.pc + 1);
-        self.clock += 4;
+        let operand = self.read(self.pc_plus(1));
+        self.read(operand as usize); // dummy read while Y is added
         operand.wrapping_add(self.y) as usize
     }
 
+    // The CPU adds the index to the low byte of the address first and reads from there while it works out the high byte.
+    // If that was the wrong page, the read was a dummy and takes an extra cycle. Stores and read-modify-writes
+    // can't take back a write to the wrong address, so they always do the dummy read.
+    fn add_index(&mut self, old_address: usize, index: u8) -> usize {
+        let new_address = (old_address as u16).wrapping_add(index as u16) as usize;
+        let uncorrected_address = (old_address & 0xFF00) | (new_address & 0xFF);
+        if uncorrected_address != new_address || writes_to_indexed_address(self.opcode) {
+            self.read(uncorrected_address);
+        }
+        new_address
+    }
+
+}
+
+fn writes_to_indexed_address(opcode: u8) -> bool {
+    matches!(opcode,
+        // abs,X
+        0x1E | 0x1F | 0x3E | 0x3F | 0x5E | 0x5F | 0x7E | 0x7F | 0x9C | 0x9D | 0xDE | 0xDF | 0xFE | 0xFF
+        // abs,Y
+        | 0x1B | 0x3B | 0x5B | 0x7B | 0x99 | 0x9B | 0x9E | 0x9F | 0xDB | 0xFB
+        // (ind),Y
+        | 0x13 | 0x33 | 0x53 | 0x73 | 0x91 | 0x93 | 0xD3 | 0xF3
+    )
 }
@@ -0,0 +1,49 @@
+// Everything the 6502 core knows about the machine around it. Each read or write is one CPU cycle, and the CPU calls
+// tick() at the start of every cycle so the rest of the machine can run alongside it. The NES wiring is NesBus
+// (nes_bus.rs); FlatBus is 64KB of RAM and nothing else, for running instructions in isolation.
+pub trait Bus {
+    fn read(&mut self, address: usize) -> u8;
+    fn write(&mut self, address: usize, value: u8);
+    fn tick(&mut self) {}
+
+    // Interrupt lines, sampled at the end of every cycle. nmi() returns true once for each NMI.
+    fn nmi(&mut self) -> bool { false }
+    fn irq(&self) -> bool { false }
+
+    // Requests for the 2A03's DMA units (see dma.rs): a page to copy to OAM, taken once,
+    // and the address of a DMC sample byte, which is handed back through dmc_dma_done().
+    fn oam_dma_request(&mut self) -> Option<u8> { None }
+    fn dmc_dma_request(&self) -> Option<usize> { None }
+    fn dmc_dma_done(&mut self, _value: u8) {}
+
+    // For trace lines: memory as it can be read without side effects, and where the PPU is, if there is one.
+    fn peek(&self, _address: usize) -> Option<u8> { None }
+    fn ppu_position(&self) -> Option<(usize, usize)> { None }
+}
+
+// 64KB of RAM at every address.
+pub struct FlatBus {
+    pub ram: Vec<u8>,
+}
+
+impl FlatBus {
+    pub fn new() -> Self {
+        FlatBus {
+            ram: vec![0; 0x10000],
+        }
+    }
+}
+
+impl Bus for FlatBus {
+    fn read(&mut self, address: usize) -> u8 {
+        self.ram[address & 0xFFFF]
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        self.ram[address & 0xFFFF] = value;
+    }
+
+    fn peek(&self, address: usize) -> Option<u8> {
+        Some(self.ram[address & 0xFFFF])
+    }
+}
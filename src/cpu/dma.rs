@@ -0,0 +1,91 @@
+use super::bus::Bus;
+
+// DMA units: OAM DMA, started by a write to $4014, and the DMC's sample fetches.
+// Either one takes over the bus by halting the CPU on its next read cycle. The CPU's read still goes out on the
+// halt cycle, then gets repeated once the DMA is done. DMA reads only happen on "get" cycles and writes on "put" cycles,
+// which alternate, so a DMA may spend a cycle lining up.
+//     OAM DMA: halt, maybe an alignment cycle, then 256 get/put pairs copying a page to $2004. 513 or 514 cycles.
+//     DMC DMA: halt, a dummy cycle, maybe an alignment cycle, then the read. 3 or 4 cycles.
+// When they overlap, OAM DMA cycles count toward the DMC's halt and dummy cycles, and the DMC read takes the place
+// of one OAM read, adding 2 cycles in all.
+// The halt, dummy and alignment cycles repeat the CPU's read, which is how DMC fetches corrupt reads of $2007.
+// Controllers don't see the repeats: they only get one clock while /OE stays low over consecutive reads of $4016/$4017.
+// See https://wiki.nesdev.com/w/index.php/DMA
+
+impl<B: Bus> super::Cpu<B> {
+
+    pub fn start_oam_dma(&mut self, page: u8) {
+        self.oam_dma_page = page;
+        self.oam_dma_running = true;
+        self.dma_halt = true;
+    }
+
+    pub fn start_dmc_dma(&mut self) {
+        self.dmc_dma_running = true;
+        self.dma_halt = true;
+        self.dmc_dma_dummy = true;
+    }
+
+    // Called from read() when a DMA is waiting for the CPU to halt, with the address the CPU was about to read.
+    pub fn run_dma(&mut self, address: usize) {
+        let repeat_reads = !(address == 0x4016 || address == 0x4017);
+        if repeat_reads {
+            self.dma_read(address); // halt cycle
+        } else {
+            self.dma_idle();
+        }
+        self.dma_halt = false;
+
+        let mut oam_cycles = 0;
+        let mut oam_byte = 0;
+        while self.dmc_dma_running || self.oam_dma_running {
+            let get_cycle = self.clock & 1 == 0;
+            // The DMC can read once its halt and dummy cycles are behind it, each a cycle of its own.
+            // OAM DMA cycles count as those when both are running.
+            let dmc_ready = self.dmc_dma_running && !self.dma_halt && !self.dmc_dma_dummy;
+            if self.dma_halt {
+                self.dma_halt = false;
+            } else if self.dmc_dma_dummy {
+                self.dmc_dma_dummy = false;
+            }
+            if get_cycle && dmc_ready {
+                if let Some(address) = self.bus.dmc_dma_request() {
+                    let sample_byte = self.dma_read(address);
+                    self.bus.dmc_dma_done(sample_byte);
+                }
+                self.dmc_dma_running = false;
+            } else if get_cycle && self.oam_dma_running {
+                oam_byte = self.dma_read(((self.oam_dma_page as usize) << 8) + oam_cycles / 2);
+                oam_cycles += 1;
+            } else if !get_cycle && self.oam_dma_running && oam_cycles & 1 == 1 {
+                self.dma_write(0x2004, oam_byte);
+                oam_cycles += 1;
+                if oam_cycles == 512 {
+                    self.oam_dma_running = false;
+                }
+            } else if repeat_reads {
+                self.dma_read(address); // dummy or alignment cycle
+            } else {
+                self.dma_idle();
+            }
+        }
+    }
+
+    fn dma_read(&mut self, address: usize) -> u8 {
+        self.tick();
+        let val = self.bus.read(address);
+        self.end_cycle();
+        val
+    }
+
+    fn dma_write(&mut self, address: usize, val: u8) {
+        self.tick();
+        self.bus.write(address, val);
+        self.end_cycle();
+    }
+
+    fn dma_idle(&mut self) {
+        self.tick();
+        self.end_cycle();
+    }
+}
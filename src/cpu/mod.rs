@@ -1,12 +1,13 @@
 mod addressing_modes;
 mod opcodes;
 mod utility;
+mod trace;
+mod dma;
+pub mod bus;
 pub mod serialize;
 
-use std::cell::RefCell;
-use std::rc::Rc;
 use serde::{Serialize, Deserialize};
-use crate::cartridge::Mapper;
+use bus::Bus;
 
 // RAM locations
 const STACK_OFFSET: usize = 0x100;
@@ -23,6 +24,10 @@ const DECIMAL_FLAG: u8           = 1 << 3;
 const OVERFLOW_FLAG: u8          = 1 << 6;
 const NEGATIVE_FLAG: u8          = 1 << 7;
 
+// XAA/ANE ($8B) and LAX/LXA #imm ($AB) OR the accumulator with this before ANDing. On real chips the value varies
+// with the chip and its temperature; $EE is the common one, and the one the ProcessorTests use for both opcodes.
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
 pub enum Mode {
     ABS, ABX, ABY, ACC,
@@ -31,10 +36,10 @@ pub enum Mode {
     ZPY,
 }
 
-type AddressingFunction = fn(&mut Cpu) -> usize;
+type AddressingFunction<B> = fn(&mut Cpu<B>) -> usize;
 
 impl Mode {
-    fn get(&self) -> (AddressingFunction, usize) { // usize is number of bytes the instruction takes, used for debug printing
+    fn get<B: Bus>(&self) -> (AddressingFunction<B>, usize) { // usize is number of bytes the instruction takes, used for trace output
         match self {
             Mode::ABS => (Cpu::absolute, 3),
             Mode::ABX => (Cpu::absolute_x, 3),
@@ -53,67 +58,79 @@ impl Mode {
     }
 }
 
-pub struct Cpu {
-    mem: Vec<u8>, // CPU's RAM, $0000-$1FFF
-    a: u8,        // accumulator
-    x: u8,        // general purpose
-    y: u8,        // general purpose
-    pc: usize,    // 16-bit program counter
-    s: u8,        // stack pointer
-    p: u8,        // status
+// The 6502 core of the 2A03, including its DMA units. Everything else, the NES or otherwise, is behind the bus.
+pub struct Cpu<B: Bus> {
+    pub a: u8,        // accumulator
+    pub x: u8,        // general purpose
+    pub y: u8,        // general purpose
+    pub pc: usize,    // 16-bit program counter
+    pub s: u8,        // stack pointer
+    pub p: u8,        // status
+
+    clock: u64, // number of CPU cycles since power-on
+    opcode: u8, // instruction being executed, which some addressing modes need to know
+    halted: bool, // set by the KIL/JAM opcodes, which freeze the CPU until reset
 
-    clock: u64, // number of ticks in current cycle
-    delay: usize, // for skipping cycles during OAM DMA
-    before_clock: u64,
+    // DMA (see dma.rs)
+    dma_halt: bool, // a DMA is waiting to halt the CPU on its next read
+    oam_dma_running: bool,
+    oam_dma_page: u8,
+    dmc_dma_running: bool,
+    dmc_dma_dummy: bool, // the DMC DMA still needs its dummy cycle after halting
 
-    pub mapper: Rc<RefCell<dyn Mapper>>, // cartridge data
-    pub ppu: super::Ppu,
-    pub apu: super::Apu,
+    // interrupt polling. The prev_ values are what was seen on the cycle before the most recent one.
+    nmi_pending: bool,
+    prev_nmi_pending: bool,
+    irq_pending: bool,
+    prev_irq_pending: bool,
 
-    // controller
-    pub strobe: u8, // signals to the controller that button inputs should be read
-    pub button_states: u8, // Player 1 controller
-    button_number: u8, // counter that scans the bits of the input register serially
+    pub bus: B,
 
     opcode_table: Vec<fn(&mut Self, usize, Mode)>, // function table
     mode_table: Vec<Mode>, // address mode table
+
+    pub tracing: bool, // if set, each step() writes a nestest.log-style line for its instruction to trace_line
+    pub trace_line: String,
 }
 
-impl Cpu {
-    pub fn new(mapper: Rc<RefCell<dyn Mapper>>, ppu: super::Ppu, apu: super::Apu) -> Self {
+impl<B: Bus> Cpu<B> {
+    pub fn new(bus: B) -> Self {
         let mut cpu = Cpu{
-            mem: vec![0; 0x2000],
             a: 0, x: 0, y: 0,
             pc: 0,
-            s: 0xFD,
+            s: 0,
             p: 0x24,
             clock: 0,
-            delay: 0,
-            before_clock: 0,
-            mapper: mapper,
-            ppu: ppu,
-            apu: apu,
-            strobe: 0,
-            button_states: 0,
-            button_number: 0,
+            opcode: 0,
+            halted: false,
+            dma_halt: false,
+            oam_dma_running: false,
+            oam_dma_page: 0,
+            dmc_dma_running: false,
+            dmc_dma_dummy: false,
+            nmi_pending: false,
+            prev_nmi_pending: false,
+            irq_pending: false,
+            prev_irq_pending: false,
+            bus,
             opcode_table: vec![
         //         00        01        02        03        04        05        06        07        08        09        0A        0B        0C        0D        0E        0F
-        /*00*/  Cpu::brk, Cpu::ora, Cpu::bad, Cpu::slo, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo, Cpu::php, Cpu::ora, Cpu::asl, Cpu::nop, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo,  /*00*/
-        /*10*/  Cpu::bpl, Cpu::ora, Cpu::bad, Cpu::slo, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo, Cpu::clc, Cpu::ora, Cpu::nop, Cpu::slo, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo,  /*10*/
-        /*20*/  Cpu::jsr, Cpu::and, Cpu::bad, Cpu::rla, Cpu::bit, Cpu::and, Cpu::rol, Cpu::rla, Cpu::plp, Cpu::and, Cpu::rol, Cpu::nop, Cpu::bit, Cpu::and, Cpu::rol, Cpu::rla,  /*20*/
-        /*30*/  Cpu::bmi, Cpu::and, Cpu::bad, Cpu::rla, Cpu::nop, Cpu::and, Cpu::rol, Cpu::rla, Cpu::sec, Cpu::and, Cpu::nop, Cpu::rla, Cpu::nop, Cpu::and, Cpu::rol, Cpu::rla,  /*30*/
-        /*40*/  Cpu::rti, Cpu::eor, Cpu::bad, Cpu::sre, Cpu::nop, Cpu::eor, Cpu::lsr, Cpu::sre, Cpu::pha, Cpu::eor, Cpu::lsr, Cpu::nop, Cpu::jmp, Cpu::eor, Cpu::lsr, Cpu::sre,  /*40*/
-        /*50*/  Cpu::bvc, Cpu::eor, Cpu::bad, Cpu::sre, Cpu::nop, Cpu::eor, Cpu::lsr, Cpu::sre, Cpu::cli, Cpu::eor, Cpu::nop, Cpu::sre, Cpu::nop, Cpu::eor, Cpu::lsr, Cpu::sre,  /*50*/
-        /*60*/  Cpu::rts, Cpu::adc, Cpu::bad, Cpu::rra, Cpu::nop, Cpu::adc, Cpu::ror, Cpu::rra, Cpu::pla, Cpu::adc, Cpu::ror, Cpu::nop, Cpu::jmp, Cpu::adc, Cpu::ror, Cpu::rra,  /*60*/
-        /*70*/  Cpu::bvs, Cpu::adc, Cpu::bad, Cpu::rra, Cpu::nop, Cpu::adc, Cpu::ror, Cpu::rra, Cpu::sei, Cpu::adc, Cpu::nop, Cpu::rra, Cpu::nop, Cpu::adc, Cpu::ror, Cpu::rra,  /*70*/
-        /*80*/  Cpu::nop, Cpu::sta, Cpu::nop, Cpu::sax, Cpu::sty, Cpu::sta, Cpu::stx, Cpu::sax, Cpu::dey, Cpu::nop, Cpu::txa, Cpu::nop, Cpu::sty, Cpu::sta, Cpu::stx, Cpu::sax,  /*80*/
-        /*90*/  Cpu::bcc, Cpu::sta, Cpu::bad, Cpu::nop, Cpu::sty, Cpu::sta, Cpu::stx, Cpu::sax, Cpu::tya, Cpu::sta, Cpu::txs, Cpu::nop, Cpu::nop, Cpu::sta, Cpu::nop, Cpu::nop,  /*90*/
-        /*A0*/  Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax, Cpu::tay, Cpu::lda, Cpu::tax, Cpu::nop, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax,  /*A0*/
-        /*B0*/  Cpu::bcs, Cpu::lda, Cpu::bad, Cpu::lax, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax, Cpu::clv, Cpu::lda, Cpu::tsx, Cpu::nop, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax,  /*B0*/
-        /*C0*/  Cpu::cpy, Cpu::cmp, Cpu::nop, Cpu::dcp, Cpu::cpy, Cpu::cmp, Cpu::dec, Cpu::dcp, Cpu::iny, Cpu::cmp, Cpu::dex, Cpu::nop, Cpu::cpy, Cpu::cmp, Cpu::dec, Cpu::dcp,  /*C0*/
-        /*D0*/  Cpu::bne, Cpu::cmp, Cpu::bad, Cpu::dcp, Cpu::nop, Cpu::cmp, Cpu::dec, Cpu::dcp, Cpu::cld, Cpu::cmp, Cpu::nop, Cpu::dcp, Cpu::nop, Cpu::cmp, Cpu::dec, Cpu::dcp,  /*D0*/
+        /*00*/  Cpu::brk, Cpu::ora, Cpu::kil, Cpu::slo, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo, Cpu::php, Cpu::ora, Cpu::asl, Cpu::anc, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo,  /*00*/
+        /*10*/  Cpu::bpl, Cpu::ora, Cpu::kil, Cpu::slo, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo, Cpu::clc, Cpu::ora, Cpu::nop, Cpu::slo, Cpu::nop, Cpu::ora, Cpu::asl, Cpu::slo,  /*10*/
+        /*20*/  Cpu::jsr, Cpu::and, Cpu::kil, Cpu::rla, Cpu::bit, Cpu::and, Cpu::rol, Cpu::rla, Cpu::plp, Cpu::and, Cpu::rol, Cpu::anc, Cpu::bit, Cpu::and, Cpu::rol, Cpu::rla,  /*20*/
+        /*30*/  Cpu::bmi, Cpu::and, Cpu::kil, Cpu::rla, Cpu::nop, Cpu::and, Cpu::rol, Cpu::rla, Cpu::sec, Cpu::and, Cpu::nop, Cpu::rla, Cpu::nop, Cpu::and, Cpu::rol, Cpu::rla,  /*30*/
+        /*40*/  Cpu::rti, Cpu::eor, Cpu::kil, Cpu::sre, Cpu::nop, Cpu::eor, Cpu::lsr, Cpu::sre, Cpu::pha, Cpu::eor, Cpu::lsr, Cpu::alr, Cpu::jmp, Cpu::eor, Cpu::lsr, Cpu::sre,  /*40*/
+        /*50*/  Cpu::bvc, Cpu::eor, Cpu::kil, Cpu::sre, Cpu::nop, Cpu::eor, Cpu::lsr, Cpu::sre, Cpu::cli, Cpu::eor, Cpu::nop, Cpu::sre, Cpu::nop, Cpu::eor, Cpu::lsr, Cpu::sre,  /*50*/
+        /*60*/  Cpu::rts, Cpu::adc, Cpu::kil, Cpu::rra, Cpu::nop, Cpu::adc, Cpu::ror, Cpu::rra, Cpu::pla, Cpu::adc, Cpu::ror, Cpu::arr, Cpu::jmp, Cpu::adc, Cpu::ror, Cpu::rra,  /*60*/
+        /*70*/  Cpu::bvs, Cpu::adc, Cpu::kil, Cpu::rra, Cpu::nop, Cpu::adc, Cpu::ror, Cpu::rra, Cpu::sei, Cpu::adc, Cpu::nop, Cpu::rra, Cpu::nop, Cpu::adc, Cpu::ror, Cpu::rra,  /*70*/
+        /*80*/  Cpu::nop, Cpu::sta, Cpu::nop, Cpu::sax, Cpu::sty, Cpu::sta, Cpu::stx, Cpu::sax, Cpu::dey, Cpu::nop, Cpu::txa, Cpu::xaa, Cpu::sty, Cpu::sta, Cpu::stx, Cpu::sax,  /*80*/
+        /*90*/  Cpu::bcc, Cpu::sta, Cpu::kil, Cpu::ahx, Cpu::sty, Cpu::sta, Cpu::stx, Cpu::sax, Cpu::tya, Cpu::sta, Cpu::txs, Cpu::tas, Cpu::shy, Cpu::sta, Cpu::shx, Cpu::ahx,  /*90*/
+        /*A0*/  Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax, Cpu::tay, Cpu::lda, Cpu::tax, Cpu::lax, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax,  /*A0*/
+        /*B0*/  Cpu::bcs, Cpu::lda, Cpu::kil, Cpu::lax, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax, Cpu::clv, Cpu::lda, Cpu::tsx, Cpu::las, Cpu::ldy, Cpu::lda, Cpu::ldx, Cpu::lax,  /*B0*/
+        /*C0*/  Cpu::cpy, Cpu::cmp, Cpu::nop, Cpu::dcp, Cpu::cpy, Cpu::cmp, Cpu::dec, Cpu::dcp, Cpu::iny, Cpu::cmp, Cpu::dex, Cpu::axs, Cpu::cpy, Cpu::cmp, Cpu::dec, Cpu::dcp,  /*C0*/
+        /*D0*/  Cpu::bne, Cpu::cmp, Cpu::kil, Cpu::dcp, Cpu::nop, Cpu::cmp, Cpu::dec, Cpu::dcp, Cpu::cld, Cpu::cmp, Cpu::nop, Cpu::dcp, Cpu::nop, Cpu::cmp, Cpu::dec, Cpu::dcp,  /*D0*/
         /*E0*/  Cpu::cpx, Cpu::sbc, Cpu::nop, Cpu::isc, Cpu::cpx, Cpu::sbc, Cpu::inc, Cpu::isc, Cpu::inx, Cpu::sbc, Cpu::nop, Cpu::sbc, Cpu::cpx, Cpu::sbc, Cpu::inc, Cpu::isc,  /*E0*/
-        /*F0*/  Cpu::beq, Cpu::sbc, Cpu::bad, Cpu::isc, Cpu::nop, Cpu::sbc, Cpu::inc, Cpu::isc, Cpu::sed, Cpu::sbc, Cpu::nop, Cpu::isc, Cpu::nop, Cpu::sbc, Cpu::inc, Cpu::isc,  /*F0*/
+        /*F0*/  Cpu::beq, Cpu::sbc, Cpu::kil, Cpu::isc, Cpu::nop, Cpu::sbc, Cpu::inc, Cpu::isc, Cpu::sed, Cpu::sbc, Cpu::nop, Cpu::isc, Cpu::nop, Cpu::sbc, Cpu::inc, Cpu::isc,  /*F0*/
             ],
             mode_table: vec![
         //          00         01         02         03         04         05         06         07         08         09         0A         0B         0C         0D         0E         0F
@@ -134,48 +151,70 @@ impl Cpu {
         /*E0*/  Mode::IMM, Mode::IDX, Mode::IMM, Mode::IDX, Mode::ZPG, Mode::ZPG, Mode::ZPG, Mode::ZPG, Mode::IMP, Mode::IMM, Mode::IMP, Mode::IMM, Mode::ABS, Mode::ABS, Mode::ABS, Mode::ABS,  /*E0*/
         /*F0*/  Mode::REL, Mode::INY, Mode::IMP, Mode::INY, Mode::ZPX, Mode::ZPX, Mode::ZPX, Mode::ZPX, Mode::IMP, Mode::ABY, Mode::IMP, Mode::ABY, Mode::ABX, Mode::ABX, Mode::ABX, Mode::ABX,  /*F0*/
             ],
+            tracing: false,
+            trace_line: String::new(),
         };
-        cpu.pc = ((cpu.read(RESET_VECTOR + 1) as usize) << 8) + cpu.read(RESET_VECTOR) as usize;
+        cpu.reset_sequence();
         cpu
     }
 
-    pub fn step(&mut self) -> u64 {
+    // The reset button. RAM and the registers are left as they were, apart from what the reset sequence changes.
+    pub fn reset(&mut self) {
+        self.halted = false;
+        self.dma_halt = false;
+        self.oam_dma_running = false;
+        self.dmc_dma_running = false;
+        self.dmc_dma_dummy = false;
+        self.nmi_pending = false;
+        self.prev_nmi_pending = false;
+        self.irq_pending = false;
+        self.prev_irq_pending = false;
+        self.reset_sequence();
+    }
 
-        // The CPU is stalled for up to 4 CPU cycles to allow the longest possible write (the return address and write after an IRQ) to finish.
-        // If OAM DMA is in progress, it is paused for two cycles. The sample fetch always occurs on an even CPU cycle due to its alignment with the APU.
-        // Specific delay cases:
-        //     4 cycles if it falls on a CPU read cycle.
-        //     3 cycles if it falls on a single CPU write cycle (or the second write of a double CPU write).
-        //     4 cycles if it falls on the first write of a double CPU write cycle.[4]
-        //     2 cycles if it occurs during an OAM DMA, or on the $4014 write cycle that triggers the OAM DMA.
-        //     1 cycle if it occurs on the second-last OAM DMA cycle.
-        //     3 cycles if it occurs on the last OAM DMA cycle.
-        if self.apu.dmc.cpu_stall {
-            self.delay = 3; // TODO: not correct
-            self.apu.dmc.cpu_stall = false;
-        }
-        
-        // skip cycles from OAM DMA if necessary
-        if self.delay > 0 {
-            self.delay -= 1;
-            return 1;
+    // The reset sequence is an interrupt whose three pushes are turned into reads, so it takes 7 cycles,
+    // moves the stack pointer down by 3 (to $FD at power-on) and sets the I flag.
+    fn reset_sequence(&mut self) {
+        self.read(self.pc);
+        self.read(self.pc);
+        for _ in 0..3 {
+            self.read(STACK_OFFSET + self.s as usize);
+            self.s = self.s.wrapping_sub(1);
         }
+        self.p |= INTERRUPT_DISABLE_FLAG;
+        self.pc = self.read(RESET_VECTOR) as usize;
+        self.pc += (self.read(RESET_VECTOR + 1) as usize) << 8;
+    }
 
+    // Runs one instruction, ticking the PPU and APU along with each of its cycles. Returns how many cycles it took.
+    pub fn step(&mut self) -> u64 {
         // back up clock so we know how many cycles we complete
         let clock = self.clock;
-        self.before_clock = self.clock;
 
-        // interrupts happen after clock is backed up because they advance it
-        self.handle_interrupts();
+        // a jammed CPU does nothing at all, but the rest of the console keeps running
+        if self.halted {
+            self.tick();
+            return 1;
+        }
+
+        // service an interrupt seen during the last instruction. The first instruction of the handler always runs
+        // before another interrupt can be taken.
+        if self.prev_nmi_pending || self.prev_irq_pending {
+            self.interrupt();
+        }
 
+        if self.tracing {
+            self.trace_line = self.trace();
+        }
         // read program counter
-        let opcode = <usize>::from(self.read(self.pc));
+        self.opcode = self.read(self.pc);
+        let opcode = self.opcode as usize;
 
         // get addressing mode
-        let mode = self.mode_table[opcode].clone();
+        let mode = self.mode_table[opcode];
         let (address_func, _num_bytes) = mode.get();
-        let address = address_func(self);
-        // self._debug(_num_bytes, opcode);
+        // JSR fetches the high byte of its target last, after pushing the return address, so it reads its own operand
+        let address = if opcode == 0x20 { self.pc_plus(1) } else { address_func(self) };
         // advance program counter according to how many bytes that instruction operated on
         self.advance_pc(mode);
         // look up instruction in table and execute
@@ -185,181 +224,61 @@ impl Cpu {
         self.clock - clock
     }
 
-    // memory interface
-    pub fn read(&mut self, address: usize) -> u8 {
-        let val = match address {
-            0x0000..=0x1FFF => self.mem[address % 0x0800],
-            0x2000..=0x3FFF => self.read_ppu_reg(address % 8),
-            0x4014          => self.read_ppu_reg(8),
-            0x4015          => self.apu.read_status(),
-            0x4016          => self.read_controller(),
-            0x4000..=0x4017 => 0, // can't read from these APU registers
-            0x4018..=0x401F => 0, // APU and I/O functionality that is normally disabled. See CPU Test Mode.
-            0x4020..=0xFFFF => self.mapper.borrow().read(address),
-            _ => panic!("invalid read from 0x{:02x}", address),
-        };
-        val
+    // True if a KIL opcode has locked up the CPU.
+    pub fn halted(&self) -> bool {
+        self.halted
     }
 
-    // memory interface
-    fn write(&mut self, address: usize, val: u8) {
-        match address {
-            0x0000..=0x1FFF => self.mem[address % 0x0800] = val,
-            0x2000..=0x3FFF => self.write_ppu_reg(address % 8, val),
-            0x4014          => self.write_ppu_reg(8, val),
-            0x4016          => self.write_controller(val),
-            0x4000..=0x4017 => self.apu.write_reg(address, val),
-            0x4018..=0x401F => (), // APU and I/O functionality that is normally disabled. See CPU Test Mode.
-            0x4020..=0xFFFF => self.mapper.borrow_mut().write(address, val),
-            _ => panic!("invalid write to {:02x}", address),
-        }
+    pub fn set_pc(&mut self, address: usize) {
+        self.pc = address;
     }
 
-    fn read_controller(&mut self) -> u8 {
-        let bit = match self.button_number < 8 {
-            true => (self.button_states & (1<<self.button_number) != 0) as u8,
-            false => 1,
-        };
-        if self.strobe & 1 != 0 {
-            self.button_number = 0;
-        } else {
-            self.button_number += 1;
-        }
-        bit | 0x40
+    // Number of cycles since power-on.
+    pub fn cycles(&self) -> u64 {
+        self.clock
     }
 
-    fn write_controller(&mut self, val: u8) {
-        self.strobe = val;
-        if self.strobe & 1 != 0 {
-            self.button_number = 0;
+    // Every read or write the CPU makes takes one cycle, and the rest of the machine gets that cycle first.
+    pub fn read(&mut self, address: usize) -> u8 {
+        if self.dma_halt {
+            self.run_dma(address);
         }
+        self.tick();
+        let val = self.bus.read(address);
+        self.end_cycle();
+        val
     }
 
-    fn read_ppu_reg(&mut self, reg_num: usize) -> u8 {
-        match reg_num {
-            2 => self.ppu.read_status(),
-            4 => self.ppu.read_oam_data(),
-            7 => self.ppu.read_data(),
-            _ => self.ppu.recent_bits,
-        }
+    fn write(&mut self, address: usize, val: u8) {
+        self.tick();
+        self.bus.write(address, val);
+        self.end_cycle();
     }
 
-    fn write_ppu_reg(&mut self, reg_num: usize, val: u8) {
-        self.ppu.recent_bits = val;
-        match reg_num {
-            0 => self.ppu.write_controller(val),
-            1 => self.ppu.write_mask(val),
-            3 => self.ppu.write_oam_address(val as usize),
-            4 => self.ppu.write_oam_data(val),
-            5 => self.ppu.write_scroll(val),
-            6 => self.ppu.write_address(val),
-            7 => self.ppu.write_data(val),
-            8 => {
-                let page = (val as usize) << 8;
-                let mut data = vec![];
-                for i in 0..=255 {
-                    data.push(self.read(page + i));
-                }
-                self.ppu.write_oam_dma(data);
-                let is_odd = self.clock % 2 != 0;
-                self.delay = 513 + if is_odd {1} else {0};
-            },
-            _ => panic!("wrote to bad ppu reg: {}", reg_num),
-        }
+    fn tick(&mut self) {
+        self.clock += 1;
+        self.bus.tick();
     }
 
-    fn handle_interrupts(&mut self) {
-        // handle interrupts from ppu
-        if self.ppu.trigger_nmi {
-            self.nmi();
-        }
-        self.ppu.trigger_nmi = false;
-        // and apu
-        if self.apu.trigger_irq && (self.p & INTERRUPT_DISABLE_FLAG == 0) {
-            self.irq();
+    // At the end of every cycle the DMA units pick up any requests, and the CPU samples its interrupt lines.
+    fn end_cycle(&mut self) {
+        if let Some(page) = self.bus.oam_dma_request() {
+            self.start_oam_dma(page);
         }
-        self.apu.trigger_irq = false;
-        // and mapper MMC3
-        if self.mapper.borrow_mut().check_irq() && (self.p & INTERRUPT_DISABLE_FLAG == 0) {
-            self.irq();
+        if self.bus.dmc_dma_request().is_some() && !self.dmc_dma_running {
+            self.start_dmc_dma();
         }
-        // TODO: should checks for APU and MMC3 IRQs be combined and acknowledged together?
-        
-        // At any time, if the interrupt flag is set, the CPU's IRQ line is continuously asserted until the interrupt flag is cleared.
-        // The processor will continue on from where it was stalled.
-        if self.apu.dmc.interrupt {
-            self.irq();
-        }
-    }
-
-    fn _debug(&mut self, num_bytes: usize, opcode: usize) {
-        let pc = self.pc;
-        let operands = match num_bytes {
-            1 => "     ".to_string(),
-            2 => format!("{:02X}   ", self.read(pc + 1)),
-            3 => format!("{:02X} {:02X}", self.read(pc + 1), self.read(pc+2)),
-            _ => "error".to_string(),
-        };
-        println!("{:04X}  {:02X} {}  {}           A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
-            pc, self.read(pc), operands, _OPCODE_DISPLAY_NAMES[opcode],
-            self.a, self.x, self.y, self.p, self.s,
-        );
+        self.poll_interrupts();
     }
 
-    pub fn _memory_at(&mut self, address: usize, amount: usize) -> Vec<u8> {
-        let mut ret = vec![];
-        for i in 0..amount {
-            ret.push(self.read(address+i));
+    // The CPU checks for interrupts at the end of every cycle, but acts on what it saw at the end of an instruction's
+    // second-to-last cycle, so anything that changes during the last cycle waits for the next instruction.
+    fn poll_interrupts(&mut self) {
+        self.prev_nmi_pending = self.nmi_pending;
+        if self.bus.nmi() {
+            self.nmi_pending = true; // stays set until the NMI is serviced
         }
-        ret
+        self.prev_irq_pending = self.irq_pending;
+        self.irq_pending = self.bus.irq() && self.p & INTERRUPT_DISABLE_FLAG == 0;
     }
 }
-
-/*
-Address range 	Size 	Device
-$0000-$07FF 	$0800 	2KB internal RAM
-$0800-$0FFF 	$0800 	]---+
-$1000-$17FF 	$0800       |---- Mirrors of $0000-$07FF
-$1800-$1FFF 	$0800   ]---+
-$2000-$2007 	$0008 	NES PPU registers
-$2008-$3FFF 	$1FF8 	Mirrors of $2000-2007 (repeats every 8 bytes)
-$4000-$4017 	$0018 	NES APU and I/O registers
-$4018-$401F 	$0008 	APU and I/O functionality that is normally disabled. See CPU Test Mode.
-$4020-$FFFF 	$BFE0 	Cartridge space: PRG ROM, PRG RAM, and mapper registers (See Note)
-*/
-
-// For debug output
-const _OPCODE_DISPLAY_NAMES: [&str; 256] = [
-    "BRK", "ORA", "BAD", "SLO", "NOP", "ORA", "ASL", "SLO",
-    "PHP", "ORA", "ASL", "ANC", "NOP", "ORA", "ASL", "SLO",
-    "BPL", "ORA", "BAD", "SLO", "NOP", "ORA", "ASL", "SLO",
-    "CLC", "ORA", "NOP", "SLO", "NOP", "ORA", "ASL", "SLO",
-    "JSR", "AND", "BAD", "RLA", "BIT", "AND", "ROL", "RLA",
-    "PLP", "AND", "ROL", "ANC", "BIT", "AND", "ROL", "RLA",
-    "BMI", "AND", "BAD", "RLA", "NOP", "AND", "ROL", "RLA",
-    "SEC", "AND", "NOP", "RLA", "NOP", "AND", "ROL", "RLA",
-    "RTI", "EOR", "BAD", "SRE", "NOP", "EOR", "LSR", "SRE",
-    "PHA", "EOR", "LSR", "ALR", "JMP", "EOR", "LSR", "SRE",
-    "BVC", "EOR", "BAD", "SRE", "NOP", "EOR", "LSR", "SRE",
-    "CLI", "EOR", "NOP", "SRE", "NOP", "EOR", "LSR", "SRE",
-    "RTS", "ADC", "BAD", "RRA", "NOP", "ADC", "ROR", "RRA",
-    "PLA", "ADC", "ROR", "ARR", "JMP", "ADC", "ROR", "RRA",
-    "BVS", "ADC", "BAD", "RRA", "NOP", "ADC", "ROR", "RRA",
-    "SEI", "ADC", "NOP", "RRA", "NOP", "ADC", "ROR", "RRA",
-    "NOP", "STA", "NOP", "SAX", "STY", "STA", "STX", "SAX",
-    "DEY", "NOP", "TXA", "XAA", "STY", "STA", "STX", "SAX",
-    "BCC", "STA", "BAD", "AHX", "STY", "STA", "STX", "SAX",
-    "TYA", "STA", "TXS", "TAS", "SHY", "STA", "SHX", "AHX",
-    "LDY", "LDA", "LDX", "LAX", "LDY", "LDA", "LDX", "LAX",
-    "TAY", "LDA", "TAX", "LAX", "LDY", "LDA", "LDX", "LAX",
-    "BCS", "LDA", "BAD", "LAX", "LDY", "LDA", "LDX", "LAX",
-    "CLV", "LDA", "TSX", "LAS", "LDY", "LDA", "LDX", "LAX",
-    "CPY", "CMP", "NOP", "DCP", "CPY", "CMP", "DEC", "DCP",
-    "INY", "CMP", "DEX", "AXS", "CPY", "CMP", "DEC", "DCP",
-    "BNE", "CMP", "BAD", "DCP", "NOP", "CMP", "DEC", "DCP",
-    "CLD", "CMP", "NOP", "DCP", "NOP", "CMP", "DEC", "DCP",
-    "CPX", "SBC", "NOP", "ISC", "CPX", "SBC", "INC", "ISC",
-    "INY", "SBC", "NOP", "SBC", "CPX", "SBC", "INC", "ISC",
-    "BEQ", "SBC", "BAD", "ISC", "NOP", "SBC", "INC", "ISC",
-    "SED", "SBC", "NOP", "ISC", "NOP", "SBC", "INC", "ISC",
-];
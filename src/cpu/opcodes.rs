@@ -1,32 +1,31 @@
-use super::{CARRY_FLAG, DECIMAL_FLAG, INTERRUPT_DISABLE_FLAG, IRQ_VECTOR, NEGATIVE_FLAG, NMI_VECTOR, OVERFLOW_FLAG, ZERO_FLAG, Mode};
+use super::{Cpu, bus::Bus, CARRY_FLAG, DECIMAL_FLAG, INTERRUPT_DISABLE_FLAG, IRQ_VECTOR, NEGATIVE_FLAG, NMI_VECTOR, OVERFLOW_FLAG, STACK_OFFSET, ZERO_FLAG, UNSTABLE_MAGIC, Mode};
 
-// TODO: check unofficial opcodes for page crosses
-
-impl super::Cpu {
+impl<B: Bus> super::Cpu<B> {
 
     pub fn adc(&mut self, _address: usize, _mode: Mode) {
         let byte = self.read(_address);
-        let carry_bit = if self.p & CARRY_FLAG == 0 {0} else {1};
-        let mut new_val = self.a.wrapping_add(byte); // add the byte at the _address to accum
-        new_val = new_val.wrapping_add(carry_bit); // add carry flag to accumulator
-        // set carry flag if we wrapped around and added something
-        if new_val <= self.a && (byte != 0 || carry_bit != 0) {
+        self.add_with_carry(byte);
+    }
+
+    pub fn ahx(&mut self, _address: usize, _mode: Mode) {
+        // unofficial, also called SHA: stores A & X & (high byte of the base address + 1)
+        self.unstable_store(_address, self.y, self.a & self.x);
+    }
+
+    pub fn alr(&mut self, _address: usize, _mode: Mode) {
+        // unofficial AND #imm then LSR A
+        self.and(_address, _mode);
+        self.lsr(_address, Mode::ACC);
+    }
+
+    pub fn anc(&mut self, _address: usize, _mode: Mode) {
+        // unofficial AND #imm, then bit 7 of the result is copied into carry as if it had been shifted out by ASL
+        self.and(_address, _mode);
+        if self.a & 0x80 != 0 {
             self.p |= CARRY_FLAG;
         } else {
             self.p &= 0xFF - CARRY_FLAG;
         }
-        self.set_zero_flag(new_val);
-        self.set_negative_flag(new_val);
-        // signed 8-bit overflow can only happen if both signs were positive but result was negative, or if both signs were negative and the result was positive
-        // sign is positive if num & 0x80 == 0, negative if num & 0x80 != 0
-        // ((sum & 0x80 != 0) && (acc & 0x80 == 0) && (operand & 0x80 == 0)) || ((sum & 0x80 == 0) && (acc & 0x80 != 0) && (operand & 0x80 != 0))
-        // simplifies to the below, thanks http://www.righto.com/2012/12/the-6502-overflow-flag-explained.html
-        if (byte ^ new_val) & (self.a ^ new_val) & 0x80 != 0 {
-            self.p |= OVERFLOW_FLAG;
-        } else {
-            self.p &= 0xFF - OVERFLOW_FLAG;
-        }
-        self.a = new_val; // actually change the accumulator
     }
 
     pub fn and(&mut self, _address: usize, _mode: Mode) {
@@ -35,27 +34,33 @@ impl super::Cpu {
         self.set_negative_flag(self.a);
     }
 
-    pub fn asl(&mut self, _address: usize, _mode: Mode) {
-        let mut val = match _mode {
-            Mode::ACC => self.a,
-            _ => {
-                self.clock += 2;
-                self.read(_address)
-            },
-        };
-        // put top bit in carry flag
-        if val & (1<<7) != 0 {
+    pub fn arr(&mut self, _address: usize, _mode: Mode) {
+        // unofficial AND #imm then ROR A, except that carry and overflow come from the adder:
+        // C is bit 6 of the result, and V is bit 6 xor bit 5.
+        self.and(_address, _mode);
+        self.ror(_address, Mode::ACC);
+        if self.a & 0x40 != 0 {
             self.p |= CARRY_FLAG;
         } else {
             self.p &= 0xFF - CARRY_FLAG;
         }
-        val <<= 1;
-        match _mode {
-            Mode::ACC => self.a = val,
-            _ => self.write(_address, val),
-        };
-        self.set_zero_flag(val);
-        self.set_negative_flag(val);
+        if ((self.a >> 6) ^ (self.a >> 5)) & 1 != 0 {
+            self.p |= OVERFLOW_FLAG;
+        } else {
+            self.p &= 0xFF - OVERFLOW_FLAG;
+        }
+    }
+
+    pub fn asl(&mut self, _address: usize, _mode: Mode) {
+        self.read_modify_write(_address, _mode, Cpu::shift_left);
+    }
+
+    pub fn axs(&mut self, _address: usize, _mode: Mode) {
+        // unofficial, also called SBX: X = (A & X) - #imm, setting flags like CMP and ignoring the carry going in
+        let byte = self.read(_address);
+        let val = self.a & self.x;
+        self.compare(val, byte);
+        self.x = val.wrapping_sub(byte);
     }
 
     pub fn bcc(&mut self, _address: usize, _mode: Mode) {
@@ -130,13 +135,15 @@ impl super::Cpu {
         // routines called by BRK always return 2 bytes after the actual BRK opcode,
         // and not just 1.
 
-        self.push(((self.pc + 1) >> 8) as u8); // push high byte
-        self.push(((self.pc + 1) & 0xFF) as u8); // push low byte
+        self.push((self.pc_plus(1) >> 8) as u8); // push high byte
+        self.push((self.pc_plus(1) & 0xFF) as u8); // push low byte
+        let vector = self.interrupt_vector();
         self.push(self.p | 0b00110000); // push status register with break bits set
         self.p |= INTERRUPT_DISABLE_FLAG; // set interrupt disable flag
-        self.pc = ((self.read(IRQ_VECTOR + 1) as usize) << 8) // set program counter to IRQ/BRK vector, taking high byte
-            + (self.read(IRQ_VECTOR) as usize); // and low byte
-        self.clock += 5; // total of 7 cycles, 2 come from implied()
+        self.pc = self.read(vector) as usize; // set program counter to IRQ/BRK vector, taking low byte
+        self.pc += (self.read(vector + 1) as usize) << 8; // and high byte
+        // like any interrupt, the handler's first instruction runs before an NMI seen during the sequence is serviced
+        self.prev_nmi_pending = false;
     }
 
     pub fn bvc(&mut self, _address: usize, _mode: Mode) {
@@ -186,17 +193,12 @@ impl super::Cpu {
 
     pub fn dcp(&mut self, _address: usize, _mode: Mode) {
         // unofficial
-        let val = self.read(_address).wrapping_sub(1);
-        self.write(_address, val);
+        let val = self.read_modify_write(_address, _mode, Cpu::decrement);
         self.compare(self.a, val);
     }
 
     pub fn dec(&mut self, _address: usize, _mode: Mode) {
-        let val = self.read(_address).wrapping_sub(1);
-        self.write(_address, val);
-        self.set_zero_flag(val);
-        self.set_negative_flag(val);
-        self.clock += 2; // extra cycles for all addressing modes of this instruction
+        self.read_modify_write(_address, _mode, Cpu::decrement);
     }
 
     pub fn dex(&mut self, _address: usize, _mode: Mode) {
@@ -218,17 +220,13 @@ impl super::Cpu {
     }
 
     pub fn inc(&mut self, _address: usize, _mode: Mode) {
-        let val = self.read(_address).wrapping_add(1);
-        self.write(_address, val);
-        self.set_zero_flag(val);
-        self.set_negative_flag(val);
-        self.clock += 2; // extra cycles for all addressing modes of this instruction
+        self.read_modify_write(_address, _mode, Cpu::increment);
     }
 
     pub fn isc(&mut self, _address: usize, _mode: Mode) {
         // unofficial
-        self.inc(_address, _mode);
-        self.sbc(_address, _mode);
+        let val = self.read_modify_write(_address, _mode, Cpu::increment);
+        self.add_with_carry(!val); // SBC
     }
 
     pub fn inx(&mut self, _address: usize, _mode: Mode) {
@@ -244,25 +242,45 @@ impl super::Cpu {
     }
 
     pub fn jmp(&mut self, _address: usize, _mode: Mode) {
-        if _mode == Mode::ABS {
-            self.clock -= 1; // this only takes 3..
-        }
         self.pc = _address;
     }
 
     pub fn jsr(&mut self, _address: usize, _mode: Mode) {
-        // call to absolute already advances program counter by 3
-        self.clock += 2;
-        let minus1 = self.pc - 1; // so m1 is the last _byte of the jsr instruction. second _byte of the operand.
+        // _address is the operand itself, since JSR reads it around the pushes: low byte, an internal cycle on the stack,
+        // the return address, and then the high byte. The program counter has already been advanced by 3.
+        let low_byte = self.read(_address) as usize;
+        self.read(STACK_OFFSET + self.s as usize); // internal cycle
+        let minus1 = (self.pc as u16).wrapping_sub(1) as usize; // so m1 is the last _byte of the jsr instruction. second _byte of the operand.
         self.push((minus1 >> 8) as u8);
         self.push((minus1 & 0xFF) as u8);
-        self.pc = _address;
+        let high_byte = self.read(minus1) as usize;
+        self.pc = (high_byte << 8) + low_byte;
+    }
+
+    pub fn kil(&mut self, _address: usize, _mode: Mode) {
+        // unofficial, also called JAM: the CPU locks up, stops fetching instructions and ignores interrupts.
+        // Only a reset gets it going again.
+        self.halted = true;
+    }
+
+    pub fn las(&mut self, _address: usize, _mode: Mode) {
+        // unofficial: A, X and the stack pointer all get memory & stack pointer
+        let byte = self.read(_address) & self.s;
+        self.a = byte;
+        self.x = byte;
+        self.s = byte;
+        self.set_zero_flag(byte);
+        self.set_negative_flag(byte);
     }
 
     pub fn lax(&mut self, _address: usize, _mode: Mode) {
         // unofficial opcode that sets both X and accumulator
         // TODO: check cycle count? https://wiki.nesdev.com/w/index.php/Programming_with_unofficial_opcodes
-        let byte = self.read(_address);
+        // The immediate version ($AB, also called ATX or LXA) is unstable like XAA: A = X = (A | magic) & #imm.
+        let mut byte = self.read(_address);
+        if _mode == Mode::IMM {
+            byte &= self.a | UNSTABLE_MAGIC;
+        }
         self.a = byte;
         self.x = byte;
         self.set_zero_flag(byte);
@@ -291,28 +309,14 @@ impl super::Cpu {
     }
 
     pub fn lsr(&mut self, _address: usize, _mode: Mode) {
-        let mut val = match _mode {
-            Mode::ACC => self.a,
-            _ => {
-                self.clock += 2;
-                self.read(_address)
-            },
-        };
-        if val & 0x1 == 0x1 {
-            self.p |= CARRY_FLAG;
-        } else {
-            self.p &= 0xFF - CARRY_FLAG;
-        }
-        val >>= 1;
-        match _mode {
-            Mode::ACC => self.a = val,
-            _ => self.write(_address, val),
-        };
-        self.set_zero_flag(val);
-        self.set_negative_flag(val);
+        self.read_modify_write(_address, _mode, Cpu::shift_right);
     }
 
     pub fn nop(&mut self, _address: usize, _mode: Mode) {
+        // the unofficial NOPs with an operand still read it
+        if _mode != Mode::IMP {
+            self.read(_address);
+        }
     }
 
     pub fn ora(&mut self, _address: usize, _mode: Mode) {
@@ -322,24 +326,22 @@ impl super::Cpu {
     }
 
     pub fn pha(&mut self, _address: usize, _mode: Mode) {
-        self.clock += 1;
         self.push(self.a);
     }
 
     pub fn php(&mut self, _address: usize, _mode: Mode) {
-        self.clock += 1;
         self.push(self.p | 0b00110000);
     }
 
     pub fn pla(&mut self, _address: usize, _mode: Mode) {
-        self.clock += 2;
+        self.read(STACK_OFFSET + self.s as usize); // dummy read while S is incremented
         self.a = self.pop();
         self.set_zero_flag(self.a);
         self.set_negative_flag(self.a);
     }
 
     pub fn plp(&mut self, _address: usize, _mode: Mode) {
-        self.clock += 2;
+        self.read(STACK_OFFSET + self.s as usize); // dummy read while S is incremented
         self.p = self.pop();
         // TODO: figure out exactly what's supposed to happen here
         // let status = self.pop();
@@ -363,71 +365,38 @@ impl super::Cpu {
 
     pub fn rla(&mut self, _address: usize, _mode: Mode) {
         // unofficial
-        self.rol(_address, _mode);
-        self.and(_address, _mode);
+        let val = self.read_modify_write(_address, _mode, Cpu::rotate_left);
+        self.a &= val;
+        self.set_zero_flag(self.a);
+        self.set_negative_flag(self.a);
     }
 
     pub fn rol(&mut self, _address: usize, _mode: Mode) {
-        let mut val = match _mode {
-            Mode::ACC => self.a,
-            _ => {
-                self.clock += 2;
-                self.read(_address)
-            },
-        };
-        let carry_flag_bit = if self.p & CARRY_FLAG != 0 {1} else {0};
-        let new_cfb = if val & 0x80 != 0 {1} else {0};
-        val <<= 1;
-        val += carry_flag_bit;
-        match _mode {
-            Mode::ACC => self.a = val,
-            _ => self.write(_address, val),
-        };
-        if new_cfb != 0 { self.p |= CARRY_FLAG; }
-        else { self.p &= 0xFF - CARRY_FLAG; }
-        self.set_zero_flag(val);
-        self.set_negative_flag(val);
+        self.read_modify_write(_address, _mode, Cpu::rotate_left);
     }
 
     pub fn ror(&mut self, _address: usize, _mode: Mode) {
-        let mut val = match _mode {
-            Mode::ACC => self.a,
-            _ => {
-                self.clock += 2; // extra cycles
-                self.read(_address)
-            }
-        };
-        let cfb = if self.p & CARRY_FLAG != 0 {1} else {0};
-        let new_cfb = val & 0x1;
-        val >>= 1;
-        val += cfb * 0x80;
-        if new_cfb != 0 { self.p |= CARRY_FLAG; }
-        else { self.p &= 0xFF - CARRY_FLAG; }
-        match _mode {
-            Mode::ACC => self.a = val,
-            _ => self.write(_address, val),
-        };
-        self.set_zero_flag(val);
-        self.set_negative_flag(val);
+        self.read_modify_write(_address, _mode, Cpu::rotate_right);
     }
 
     pub fn rra(&mut self, _address: usize, _mode: Mode) {
         // unofficial
-        self.ror(_address, _mode);
-        self.adc(_address, _mode);
+        let val = self.read_modify_write(_address, _mode, Cpu::rotate_right);
+        self.add_with_carry(val);
     }
 
     pub fn rti(&mut self, _address: usize, _mode: Mode) {
         self.plp(_address, _mode); // pull and set status reg (2 clock cycles)
         self.pc = self.pop() as usize; // low byte
         self.pc += (self.pop() as usize) << 8; // high byte
-        self.clock += 2; // +2 from implied
     }
 
     pub fn rts(&mut self, _address: usize, _mode: Mode) {
+        self.read(STACK_OFFSET + self.s as usize); // dummy read while S is incremented
         self.pc = self.pop() as usize;
-        self.pc += ((self.pop() as usize) << 8) + 1;
-        self.clock += 4;
+        self.pc += (self.pop() as usize) << 8;
+        self.read(self.pc); // dummy read while PC is incremented
+        self.pc = self.pc_plus(1);
     }
 
     pub fn sax(&mut self, _address: usize, _mode: Mode) {
@@ -436,30 +405,9 @@ impl super::Cpu {
     }
 
     pub fn sbc(&mut self, _address: usize, _mode: Mode) {
+        // A - M - (1 - C) is the same as A + ~M + C, carry and overflow included
         let byte = self.read(_address);
-        let carry_bit = if self.p & CARRY_FLAG == 0 {1} else {0};
-        let mut new_val = self.a.wrapping_sub(byte);
-        new_val = new_val.wrapping_sub(carry_bit);
-        // if overflow occurs and we subtracted something, CLEAR the carry bit
-        if new_val >= self.a && (byte != 0 || carry_bit != 0) {
-            self.p &= 0xFF - CARRY_FLAG;
-        } else {
-            self.p |= CARRY_FLAG;
-        }
-        self.set_zero_flag(new_val);
-        self.set_negative_flag(new_val);
-        // if acc is positive, mem is negative, and result is negative
-        // or if acc is negative, mem is positive, and result is positive
-        let acc = self.a & 0x80 == 0;
-        let mem = byte & 0x80 == 0;
-        let res = new_val & 0x80 == 0;
-        // if sign is wrong, SET overflow flag
-        if (acc && !mem && !res) || (!acc && mem && res) {
-            self.p |= OVERFLOW_FLAG;
-        } else {
-            self.p &= 0xFF - OVERFLOW_FLAG;
-        }
-        self.a = new_val; // actually change the accumulator
+        self.add_with_carry(!byte);
     }
 
     pub fn sec(&mut self, _address: usize, _mode: Mode) {
@@ -474,30 +422,33 @@ impl super::Cpu {
         self.p |= INTERRUPT_DISABLE_FLAG;
     }
 
+    pub fn shx(&mut self, _address: usize, _mode: Mode) {
+        // unofficial: stores X & (high byte of the base address + 1)
+        self.unstable_store(_address, self.y, self.x);
+    }
+
+    pub fn shy(&mut self, _address: usize, _mode: Mode) {
+        // unofficial: stores Y & (high byte of the base address + 1)
+        self.unstable_store(_address, self.x, self.y);
+    }
+
     pub fn slo(&mut self, _address: usize, _mode: Mode) {
-        self.asl(_address, _mode);
-        self.ora(_address, _mode);
-        // can get away with ignoring that asl handles accumulator addressing mode because slo doesn't handle accumulator addressing mode.
+        // unofficial
+        let val = self.read_modify_write(_address, _mode, Cpu::shift_left);
+        self.a |= val;
+        self.set_zero_flag(self.a);
+        self.set_negative_flag(self.a);
     }
 
     pub fn sre(&mut self, _address: usize, _mode: Mode) {
         // unofficial
-        self.lsr(_address, _mode);
-        self.eor(_address, _mode);
+        let val = self.read_modify_write(_address, _mode, Cpu::shift_right);
+        self.a ^= val;
+        self.set_negative_flag(self.a);
+        self.set_zero_flag(self.a);
     }
 
     pub fn sta(&mut self, _address: usize, _mode: Mode) {
-        // PPU Test 17
-        // STA, $2000,Y **must** issue a dummy read to 2007
-        if _address == 0x2007 && _mode == Mode::ABY && self.y == 7 {
-            self.read(0x2007);
-        }
-
-        if _mode == Mode::INY {
-            self.clock = self.before_clock + 6; // Special
-        } else if _mode == Mode::ABY {
-            self.clock = self.before_clock + 5; // Special
-        }
         self.write(_address, self.a);
     }
 
@@ -509,6 +460,12 @@ impl super::Cpu {
         self.write(_address, self.y);
     }
 
+    pub fn tas(&mut self, _address: usize, _mode: Mode) {
+        // unofficial, also called SHS: S = A & X, then stores S & (high byte of the base address + 1)
+        self.s = self.a & self.x;
+        self.unstable_store(_address, self.y, self.s);
+    }
+
     pub fn tax(&mut self, _address: usize, _mode: Mode) {
         self.x = self.a;
         self.set_zero_flag(self.x);
@@ -543,29 +500,142 @@ impl super::Cpu {
         self.set_negative_flag(self.a);
     }
 
-    pub fn bad(&mut self, _address: usize, _mode: Mode) {
-        panic!("illegal opcode: 0x{:02X}", self.read(self.pc)); // this won't be the illegal opcode because the PC somehow hasn't been updated yet
+    pub fn xaa(&mut self, _address: usize, _mode: Mode) {
+        // unofficial, also called ANE. Highly unstable on real hardware: A = (A | magic) & X & #imm
+        let byte = self.read(_address);
+        self.a = (self.a | UNSTABLE_MAGIC) & self.x & byte;
+        self.set_zero_flag(self.a);
+        self.set_negative_flag(self.a);
     }
 
     // Interrupts
-    pub fn nmi(&mut self) {
+    // NMI and IRQ share one sequence, like BRK's with two dummy reads in place of the opcode and padding byte.
+    // "If an interrupt of higher priority (NMI) occurs before the vector fetch, the vector for NMI will be used instead."
+    pub fn interrupt(&mut self) {
+        self.read(self.pc);
+        self.read(self.pc);
         self.push((self.pc >> 8) as u8); // push high byte
         self.push((self.pc & 0xFF) as u8); // push low byte
-        self.push(self.p | 0b00110000); // push status register with break bits set
+        let vector = self.interrupt_vector();
+        self.push((self.p | 0b00100000) & 0b11101111); // push status register with B flag cleared
         self.p |= INTERRUPT_DISABLE_FLAG; // set interrupt disable flag
-        self.pc = ((self.read(NMI_VECTOR + 1) as usize) << 8) // set program counter to NMI vector, taking high byte
-            + (self.read(NMI_VECTOR) as usize); // and low byte
-        self.clock += 7;
+        self.pc = self.read(vector) as usize; // set program counter to the vector, taking low byte
+        self.pc += (self.read(vector + 1) as usize) << 8; // and high byte
     }
 
-    pub fn irq(&mut self) {
-        self.push((self.pc >> 8) as u8); // push high byte
-        self.push((self.pc & 0xFF) as u8); // push low byte
-        self.push(self.p & 0b11001111); // push status register with break bits cleared
-        self.p |= INTERRUPT_DISABLE_FLAG; // set interrupt disable flag
-        self.pc = ((self.read(IRQ_VECTOR + 1) as usize) << 8) // set program counter to IRQ/BRK vector, taking high byte
-            + (self.read(IRQ_VECTOR) as usize); // and low byte
-        self.clock += 7;
+    // Decided after the return address is pushed: an NMI that's pending by then hijacks an IRQ or BRK.
+    fn interrupt_vector(&mut self) -> usize {
+        if self.nmi_pending {
+            self.nmi_pending = false;
+            NMI_VECTOR
+        } else {
+            IRQ_VECTOR
+        }
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Cpu, bus::FlatBus, CARRY_FLAG, NEGATIVE_FLAG, OVERFLOW_FLAG, ZERO_FLAG};
+
+    // Runs one instruction from $0200 with the given registers. Memory is all zeros apart from the program.
+    fn run(program: &[u8], a: u8, x: u8, y: u8, p: u8) -> Cpu<FlatBus> {
+        let mut cpu = Cpu::new(FlatBus::new());
+        cpu.bus.ram[0x200..0x200 + program.len()].copy_from_slice(program);
+        cpu.pc = 0x200;
+        cpu.a = a;
+        cpu.x = x;
+        cpu.y = y;
+        cpu.p = p;
+        cpu.step();
+        cpu
+    }
+
+    #[test]
+    fn anc_copies_bit_7_to_carry() {
+        let cpu = run(&[0x0B, 0x80], 0xFF, 0, 0, 0x24);
+        assert_eq!(cpu.a, 0x80);
+        assert_eq!(cpu.p, 0x24 | CARRY_FLAG | NEGATIVE_FLAG);
+        let cpu = run(&[0x2B, 0x7F], 0xFF, 0, 0, 0x24 | CARRY_FLAG);
+        assert_eq!(cpu.a, 0x7F);
+        assert_eq!(cpu.p, 0x24);
+        assert_eq!(cpu.pc, 0x202);
+    }
+
+    #[test]
+    fn arr_sets_carry_and_overflow_from_bits_6_and_5() {
+        // $80 rotated right with no carry in is $40: C = bit 6 = 1, V = bit 6 ^ bit 5 = 1
+        let cpu = run(&[0x6B, 0xC0], 0x80, 0, 0, 0x24);
+        assert_eq!(cpu.a, 0x40);
+        assert_eq!(cpu.p, 0x24 | CARRY_FLAG | OVERFLOW_FLAG);
+        // $FF rotated right with carry in is $FF: C = 1, V = 0
+        let cpu = run(&[0x6B, 0xFF], 0xFF, 0, 0, 0x24 | CARRY_FLAG | OVERFLOW_FLAG);
+        assert_eq!(cpu.a, 0xFF);
+        assert_eq!(cpu.p, 0x24 | CARRY_FLAG | NEGATIVE_FLAG);
+        // $01 rotated right with no carry in is zero, and the bit shifted out doesn't reach carry
+        let cpu = run(&[0x6B, 0x01], 0xFF, 0, 0, 0x24);
+        assert_eq!(cpu.a, 0);
+        assert_eq!(cpu.p, 0x24 | ZERO_FLAG);
+    }
+
+    #[test]
+    fn axs_subtracts_from_a_and_x_like_cmp() {
+        let cpu = run(&[0xCB, 0x10], 0xF0, 0x3C, 0, 0x24);
+        assert_eq!((cpu.a, cpu.x), (0xF0, 0x20));
+        assert_eq!(cpu.p, 0x24 | CARRY_FLAG);
+        // borrow clears carry, and the carry going in is ignored
+        let cpu = run(&[0xCB, 0x40], 0xF0, 0x3C, 0, 0x24 | CARRY_FLAG);
+        assert_eq!(cpu.x, 0xF0);
+        assert_eq!(cpu.p, 0x24 | NEGATIVE_FLAG);
+        let cpu = run(&[0xCB, 0x30], 0xF0, 0x3C, 0, 0x24);
+        assert_eq!(cpu.x, 0);
+        assert_eq!(cpu.p, 0x24 | CARRY_FLAG | ZERO_FLAG);
+    }
+
+    #[test]
+    fn shx_and_shy_and_with_the_high_byte_plus_one() {
+        // SHX $1200,Y without a page cross stores X & $13 where it's told to
+        let cpu = run(&[0x9E, 0x00, 0x12], 0, 0x35, 0x10, 0x24);
+        assert_eq!(cpu.bus.ram[0x1210], 0x11);
+        // SHX $12F0,Y crossing into $13xx: the stored value replaces the high byte of the address too
+        let cpu = run(&[0x9E, 0xF0, 0x12], 0, 0x35, 0x20, 0x24);
+        assert_eq!(cpu.bus.ram[0x1310], 0);
+        assert_eq!(cpu.bus.ram[0x1110], 0x11);
+        // and SHY $xxxx,X the same way
+        let cpu = run(&[0x9C, 0x00, 0x12], 0, 0x10, 0x35, 0x24);
+        assert_eq!(cpu.bus.ram[0x1210], 0x11);
+        let cpu = run(&[0x9C, 0xF0, 0x12], 0, 0x20, 0x35, 0x24);
+        assert_eq!(cpu.bus.ram[0x1310], 0);
+        assert_eq!(cpu.bus.ram[0x1110], 0x11);
+        assert_eq!(cpu.pc, 0x203);
+    }
+
+    #[test]
+    fn lxa_and_xaa_use_the_same_magic_value() {
+        let cpu = run(&[0xAB, 0xFF], 0x00, 0x00, 0, 0x24);
+        assert_eq!((cpu.a, cpu.x), (0xEE, 0xEE));
+        let cpu = run(&[0x8B, 0xFF], 0x00, 0xFF, 0, 0x24);
+        assert_eq!(cpu.a, 0xEE);
+        // other addressing modes of LAX are plain loads
+        let mut cpu = Cpu::new(FlatBus::new());
+        cpu.bus.ram[0x200..0x202].copy_from_slice(&[0xA7, 0x10]);
+        cpu.bus.ram[0x10] = 0x01;
+        cpu.pc = 0x200;
+        cpu.step();
+        assert_eq!((cpu.a, cpu.x), (0x01, 0x01));
+    }
+
+    #[test]
+    fn kil_jams_until_reset() {
+        let mut cpu = run(&[0x02, 0xEA], 0, 0, 0, 0x24);
+        assert!(cpu.halted());
+        let pc = cpu.pc;
+        for _ in 0..10 {
+            assert_eq!(cpu.step(), 1);
+        }
+        assert_eq!(cpu.pc, pc);
+        cpu.reset();
+        assert!(!cpu.halted());
+    }
+}
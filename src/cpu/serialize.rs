@@ -1,10 +1,9 @@
-use super::Mode;
+use super::{Mode, bus::Bus};
 
 use serde::{Serialize, Deserialize};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CpuData {
-    mem: Vec<u8>,
     a: u8,
     x: u8,
     y: u8,
@@ -12,17 +11,22 @@ pub struct CpuData {
     s: u8,
     p: u8,
     clock: u64,
-    delay: usize,
-    strobe: u8,
-    button_states: u8,
-    button_number: u8,
+    halted: bool,
+    nmi_pending: bool,
+    prev_nmi_pending: bool,
+    irq_pending: bool,
+    prev_irq_pending: bool,
+    dma_halt: bool,
+    oam_dma_running: bool,
+    oam_dma_page: u8,
+    dmc_dma_running: bool,
+    dmc_dma_dummy: bool,
     mode_table: Vec<Mode>,
 }
 
-impl super::Cpu {
+impl<B: Bus> super::Cpu<B> {
     pub fn save_state(&self) -> CpuData {
         CpuData{
-            mem: self.mem.clone(),
             a: self.a,
             x: self.x,
             y: self.y,
@@ -30,16 +34,21 @@ impl super::Cpu {
             s: self.s,
             p: self.p,
             clock: self.clock,
-            delay: self.delay,
-            strobe: self.strobe,
-            button_states: self.button_states,
-            button_number: self.button_number,
+            halted: self.halted,
+            nmi_pending: self.nmi_pending,
+            prev_nmi_pending: self.prev_nmi_pending,
+            irq_pending: self.irq_pending,
+            prev_irq_pending: self.prev_irq_pending,
+            dma_halt: self.dma_halt,
+            oam_dma_running: self.oam_dma_running,
+            oam_dma_page: self.oam_dma_page,
+            dmc_dma_running: self.dmc_dma_running,
+            dmc_dma_dummy: self.dmc_dma_dummy,
             mode_table: self.mode_table.clone(),
         }
     }
 
     pub fn load_state(&mut self, data: CpuData) {
-        self.mem = data.mem;
         self.a = data.a;
         self.x = data.x;
         self.y = data.y;
@@ -47,10 +56,16 @@ impl super::Cpu {
         self.s = data.s;
         self.p = data.p;
         self.clock = data.clock;
-        self.delay = data.delay;
-        self.strobe = data.strobe;
-        self.button_states = data.button_states;
-        self.button_number = data.button_number;
+        self.halted = data.halted;
+        self.nmi_pending = data.nmi_pending;
+        self.prev_nmi_pending = data.prev_nmi_pending;
+        self.irq_pending = data.irq_pending;
+        self.prev_irq_pending = data.prev_irq_pending;
+        self.dma_halt = data.dma_halt;
+        self.oam_dma_running = data.oam_dma_running;
+        self.oam_dma_page = data.oam_dma_page;
+        self.dmc_dma_running = data.dmc_dma_running;
+        self.dmc_dma_dummy = data.dmc_dma_dummy;
         self.mode_table = data.mode_table;
     }
 }
@@ -0,0 +1,143 @@
+use super::{Mode, bus::Bus};
+
+// Produces trace lines in the format of nestest.log, so a run can be diffed against that or any other log from Nintendulator:
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+// Unofficial opcodes are marked with a * before the mnemonic.
+
+impl<B: Bus> super::Cpu<B> {
+
+    // Describe the instruction at the program counter and the state of the CPU before it runs.
+    pub fn trace(&self) -> String {
+        let pc = self.pc;
+        let opcode = self.peek(pc).unwrap_or(0) as usize;
+        let mode = self.mode_table[opcode];
+        let (_, num_bytes) = mode.get::<B>();
+        let byte = |offset: usize| self.peek((pc + offset) & 0xFFFF).unwrap_or(0);
+        let bytes = (0..num_bytes)
+            .map(|i| format!("{:02X}", byte(i)))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let low = byte(1) as usize;
+        let absolute = ((byte(2) as usize) << 8) + low;
+        let operand = match mode {
+            Mode::IMP => String::new(),
+            Mode::ACC => "A".to_string(),
+            Mode::IMM => format!("#${:02X}", low),
+            Mode::ZPG => format!("${:02X}{}", low, self.trace_value(low)),
+            Mode::ZPX => {
+                let address = (low + self.x as usize) & 0xFF;
+                format!("${:02X},X @ {:02X}{}", low, address, self.trace_value(address))
+            },
+            Mode::ZPY => {
+                let address = (low + self.y as usize) & 0xFF;
+                format!("${:02X},Y @ {:02X}{}", low, address, self.trace_value(address))
+            },
+            Mode::ABS => match opcode {
+                0x20 | 0x4C => format!("${:04X}", absolute), // JSR and JMP go there instead of operating on what's there
+                _ => format!("${:04X}{}", absolute, self.trace_value(absolute)),
+            },
+            Mode::ABX => {
+                let address = (absolute + self.x as usize) & 0xFFFF;
+                format!("${:04X},X @ {:04X}{}", absolute, address, self.trace_value(address))
+            },
+            Mode::ABY => {
+                let address = (absolute + self.y as usize) & 0xFFFF;
+                format!("${:04X},Y @ {:04X}{}", absolute, address, self.trace_value(address))
+            },
+            Mode::IND => {
+                // same page-wrapping bug as indirect()
+                let high_address = if absolute & 0xFF == 0xFF { absolute - 0xFF } else { absolute + 1 };
+                let target = self.peek_word(absolute, high_address);
+                format!("(${:04X}) = {:04X}", absolute, target)
+            },
+            Mode::IDX => {
+                let pointer = (low + self.x as usize) & 0xFF;
+                let address = self.peek_word(pointer, (pointer + 1) & 0xFF);
+                format!("(${:02X},X) @ {:02X} = {:04X}{}", low, pointer, address, self.trace_value(address))
+            },
+            Mode::INY => {
+                let base = self.peek_word(low, (low + 1) & 0xFF);
+                let address = (base + self.y as usize) & 0xFFFF;
+                format!("(${:02X}),Y = {:04X} @ {:04X}{}", low, base, address, self.trace_value(address))
+            },
+            Mode::REL => format!("${:04X}", (pc + 2).wrapping_add(low as u8 as i8 as usize) & 0xFFFF),
+        };
+        let marker = if OFFICIAL_OPCODES.contains(&(opcode as u8)) { ' ' } else { '*' };
+        let disassembly = format!("{} {}", OPCODE_DISPLAY_NAMES[opcode], operand);
+        // buses without a PPU leave its position out
+        let ppu = match self.bus.ppu_position() {
+            Some((scanline, dot)) => format!(" PPU:{:>3},{:>3}", scanline, dot),
+            None => String::new(),
+        };
+        format!("{:04X}  {:<8} {}{:<32}A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}{} CYC:{}",
+            pc, bytes, marker, disassembly.trim_end(),
+            self.a, self.x, self.y, self.p, self.s,
+            ppu, self.clock,
+        )
+    }
+
+    // Read memory for display without side effects.
+    fn peek(&self, address: usize) -> Option<u8> {
+        self.bus.peek(address)
+    }
+
+    fn peek_word(&self, low_address: usize, high_address: usize) -> usize {
+        ((self.peek(high_address).unwrap_or(0) as usize) << 8) + self.peek(low_address).unwrap_or(0) as usize
+    }
+
+    fn trace_value(&self, address: usize) -> String {
+        match self.peek(address) {
+            Some(value) => format!(" = {:02X}", value),
+            None => String::new(),
+        }
+    }
+}
+
+const OFFICIAL_OPCODES: [u8; 151] = [
+    0x00, 0x01, 0x05, 0x06, 0x08, 0x09, 0x0A, 0x0D, 0x0E, 0x10, 0x11, 0x15, 0x16, 0x18, 0x19, 0x1D,
+    0x1E, 0x20, 0x21, 0x24, 0x25, 0x26, 0x28, 0x29, 0x2A, 0x2C, 0x2D, 0x2E, 0x30, 0x31, 0x35, 0x36,
+    0x38, 0x39, 0x3D, 0x3E, 0x40, 0x41, 0x45, 0x46, 0x48, 0x49, 0x4A, 0x4C, 0x4D, 0x4E, 0x50, 0x51,
+    0x55, 0x56, 0x58, 0x59, 0x5D, 0x5E, 0x60, 0x61, 0x65, 0x66, 0x68, 0x69, 0x6A, 0x6C, 0x6D, 0x6E,
+    0x70, 0x71, 0x75, 0x76, 0x78, 0x79, 0x7D, 0x7E, 0x81, 0x84, 0x85, 0x86, 0x88, 0x8A, 0x8C, 0x8D,
+    0x8E, 0x90, 0x91, 0x94, 0x95, 0x96, 0x98, 0x99, 0x9A, 0x9D, 0xA0, 0xA1, 0xA2, 0xA4, 0xA5, 0xA6,
+    0xA8, 0xA9, 0xAA, 0xAC, 0xAD, 0xAE, 0xB0, 0xB1, 0xB4, 0xB5, 0xB6, 0xB8, 0xB9, 0xBA, 0xBC, 0xBD,
+    0xBE, 0xC0, 0xC1, 0xC4, 0xC5, 0xC6, 0xC8, 0xC9, 0xCA, 0xCC, 0xCD, 0xCE, 0xD0, 0xD1, 0xD5, 0xD6,
+    0xD8, 0xD9, 0xDD, 0xDE, 0xE0, 0xE1, 0xE4, 0xE5, 0xE6, 0xE8, 0xE9, 0xEA, 0xEC, 0xED, 0xEE, 0xF0,
+    0xF1, 0xF5, 0xF6, 0xF8, 0xF9, 0xFD, 0xFE,
+];
+
+// For trace output
+const OPCODE_DISPLAY_NAMES: [&str; 256] = [
+    "BRK", "ORA", "BAD", "SLO", "NOP", "ORA", "ASL", "SLO",
+    "PHP", "ORA", "ASL", "ANC", "NOP", "ORA", "ASL", "SLO",
+    "BPL", "ORA", "BAD", "SLO", "NOP", "ORA", "ASL", "SLO",
+    "CLC", "ORA", "NOP", "SLO", "NOP", "ORA", "ASL", "SLO",
+    "JSR", "AND", "BAD", "RLA", "BIT", "AND", "ROL", "RLA",
+    "PLP", "AND", "ROL", "ANC", "BIT", "AND", "ROL", "RLA",
+    "BMI", "AND", "BAD", "RLA", "NOP", "AND", "ROL", "RLA",
+    "SEC", "AND", "NOP", "RLA", "NOP", "AND", "ROL", "RLA",
+    "RTI", "EOR", "BAD", "SRE", "NOP", "EOR", "LSR", "SRE",
+    "PHA", "EOR", "LSR", "ALR", "JMP", "EOR", "LSR", "SRE",
+    "BVC", "EOR", "BAD", "SRE", "NOP", "EOR", "LSR", "SRE",
+    "CLI", "EOR", "NOP", "SRE", "NOP", "EOR", "LSR", "SRE",
+    "RTS", "ADC", "BAD", "RRA", "NOP", "ADC", "ROR", "RRA",
+    "PLA", "ADC", "ROR", "ARR", "JMP", "ADC", "ROR", "RRA",
+    "BVS", "ADC", "BAD", "RRA", "NOP", "ADC", "ROR", "RRA",
+    "SEI", "ADC", "NOP", "RRA", "NOP", "ADC", "ROR", "RRA",
+    "NOP", "STA", "NOP", "SAX", "STY", "STA", "STX", "SAX",
+    "DEY", "NOP", "TXA", "XAA", "STY", "STA", "STX", "SAX",
+    "BCC", "STA", "BAD", "AHX", "STY", "STA", "STX", "SAX",
+    "TYA", "STA", "TXS", "TAS", "SHY", "STA", "SHX", "AHX",
+    "LDY", "LDA", "LDX", "LAX", "LDY", "LDA", "LDX", "LAX",
+    "TAY", "LDA", "TAX", "LAX", "LDY", "LDA", "LDX", "LAX",
+    "BCS", "LDA", "BAD", "LAX", "LDY", "LDA", "LDX", "LAX",
+    "CLV", "LDA", "TSX", "LAS", "LDY", "LDA", "LDX", "LAX",
+    "CPY", "CMP", "NOP", "DCP", "CPY", "CMP", "DEC", "DCP",
+    "INY", "CMP", "DEX", "AXS", "CPY", "CMP", "DEC", "DCP",
+    "BNE", "CMP", "BAD", "DCP", "NOP", "CMP", "DEC", "DCP",
+    "CLD", "CMP", "NOP", "DCP", "NOP", "CMP", "DEC", "DCP",
+    "CPX", "SBC", "NOP", "ISC", "CPX", "SBC", "INC", "ISC",
+    "INX", "SBC", "NOP", "SBC", "CPX", "SBC", "INC", "ISC",
+    "BEQ", "SBC", "BAD", "ISC", "NOP", "SBC", "INC", "ISC",
+    "SED", "SBC", "NOP", "ISC", "NOP", "SBC", "INC", "ISC",
+];
@@ -1,9 +1,14 @@
-use super::{CARRY_FLAG, NEGATIVE_FLAG, STACK_OFFSET, ZERO_FLAG, Mode};
+use super::{bus::Bus, CARRY_FLAG, NEGATIVE_FLAG, OVERFLOW_FLAG, STACK_OFFSET, ZERO_FLAG, Mode};
 
-impl super::Cpu {
+impl<B: Bus> super::Cpu<B> {
+
+    // The address n bytes after the program counter. Like the program counter itself, it wraps around at $FFFF.
+    pub fn pc_plus(&self, n: usize) -> usize {
+        (self.pc + n) & 0xFFFF
+    }
 
     pub fn advance_pc(&mut self, mode: Mode) {
-        self.pc += match mode {
+        self.pc = self.pc_plus(match mode {
             Mode::ABS => 3,
             Mode::ABX => 3,
             Mode::ABY => 3,
@@ -17,43 +22,149 @@ impl super::Cpu {
             Mode::ZPG => 2,
             Mode::ZPX => 2,
             Mode::ZPY => 2,
-        }
+        });
     }
 
     pub fn add_offset_to_pc(&mut self, offset: i8) {
-        match offset >= 0 {
-            true => {
-                let decoded_offset = offset as usize;
-                self.pc += decoded_offset;
-            },
-            false => {
-                // instr_test-v5/rom_singles/11-stack.nes:
-                // letting decoded_offset be (-offset) as usize was allowing for overflow if offset was -128/0b10000000
-                let decoded_offset = (-offset) as u8;
-                self.pc -= decoded_offset as usize;
-            },
+        self.pc = (self.pc as u16).wrapping_add(offset as u16) as usize;
+    }
+
+    // The CPU already read the next opcode while checking the condition. Taking the branch means reading it again
+    // while the offset is added to the low byte of PC, and crossing a page means one more read before the high byte is fixed.
+    pub fn branch(&mut self, unsigned_offset: u8) {
+        let offset = unsigned_offset as i8;
+        // "a taken non-page-crossing branch ignores IRQ/NMI during its last clock, so that next instruction executes before the IRQ"
+        if self.irq_pending && !self.prev_irq_pending {
+            self.irq_pending = false;
+        }
+        // The NMI is hidden from this cycle's poll the same way, but put back afterwards since its edge is only seen once.
+        let late_nmi = self.nmi_pending && !self.prev_nmi_pending;
+        if late_nmi {
+            self.nmi_pending = false;
+        }
+        self.read(self.pc);
+        if late_nmi {
+            self.nmi_pending = true;
+        }
+        let old_addr = self.pc;
+        self.add_offset_to_pc(offset);
+        let new_addr = self.pc;
+        if old_addr >> 8 != new_addr >> 8 {
+            self.read((old_addr & 0xFF00) | (new_addr & 0xFF));
         }
     }
 
-    pub fn address_page_cross(&mut self, old_address: usize, new_address: usize) {
-        if old_address >> 8 != new_address >> 8 {
-            self.clock += 1;
+    // Read-modify-write instructions read the value, write it back unchanged while they work on it, then write the result.
+    // Returns the result so the unofficial combined instructions can use it.
+    pub fn read_modify_write(&mut self, address: usize, mode: Mode, operation: fn(&mut Self, u8) -> u8) -> u8 {
+        if mode == Mode::ACC {
+            self.a = operation(self, self.a);
+            return self.a;
         }
+        let val = self.read(address);
+        self.write(address, val);
+        let result = operation(self, val);
+        self.write(address, result);
+        result
     }
 
-    pub fn branch_page_cross(&mut self, old_address: usize, new_address: usize) {
-        if old_address >> 8 != new_address >> 8 {
-            self.clock += 1;
+    pub fn shift_left(&mut self, val: u8) -> u8 {
+        // put top bit in carry flag
+        if val & (1<<7) != 0 {
+            self.p |= CARRY_FLAG;
+        } else {
+            self.p &= 0xFF - CARRY_FLAG;
         }
+        let val = val << 1;
+        self.set_zero_flag(val);
+        self.set_negative_flag(val);
+        val
     }
 
-    pub fn branch(&mut self, unsigned_offset: u8) {
-        let offset = unsigned_offset as i8;
-        self.clock += 1;
-        let old_addr = self.pc;
-        self.add_offset_to_pc(offset);
-        let new_addr = self.pc;
-        self.branch_page_cross(old_addr, new_addr);
+    pub fn shift_right(&mut self, val: u8) -> u8 {
+        if val & 0x1 == 0x1 {
+            self.p |= CARRY_FLAG;
+        } else {
+            self.p &= 0xFF - CARRY_FLAG;
+        }
+        let val = val >> 1;
+        self.set_zero_flag(val);
+        self.set_negative_flag(val);
+        val
+    }
+
+    pub fn rotate_left(&mut self, val: u8) -> u8 {
+        let carry_flag_bit = if self.p & CARRY_FLAG != 0 {1} else {0};
+        let new_cfb = if val & 0x80 != 0 {1} else {0};
+        let val = (val << 1) + carry_flag_bit;
+        if new_cfb != 0 { self.p |= CARRY_FLAG; }
+        else { self.p &= 0xFF - CARRY_FLAG; }
+        self.set_zero_flag(val);
+        self.set_negative_flag(val);
+        val
+    }
+
+    pub fn rotate_right(&mut self, val: u8) -> u8 {
+        let cfb = if self.p & CARRY_FLAG != 0 {1} else {0};
+        let new_cfb = val & 0x1;
+        let val = (val >> 1) + cfb * 0x80;
+        if new_cfb != 0 { self.p |= CARRY_FLAG; }
+        else { self.p &= 0xFF - CARRY_FLAG; }
+        self.set_zero_flag(val);
+        self.set_negative_flag(val);
+        val
+    }
+
+    pub fn increment(&mut self, val: u8) -> u8 {
+        let val = val.wrapping_add(1);
+        self.set_zero_flag(val);
+        self.set_negative_flag(val);
+        val
+    }
+
+    pub fn decrement(&mut self, val: u8) -> u8 {
+        let val = val.wrapping_sub(1);
+        self.set_zero_flag(val);
+        self.set_negative_flag(val);
+        val
+    }
+
+    pub fn add_with_carry(&mut self, byte: u8) {
+        let carry_bit = if self.p & CARRY_FLAG == 0 {0} else {1};
+        let mut new_val = self.a.wrapping_add(byte); // add the byte to accum
+        new_val = new_val.wrapping_add(carry_bit); // add carry flag to accumulator
+        // set carry flag if we wrapped around and added something
+        if new_val <= self.a && (byte != 0 || carry_bit != 0) {
+            self.p |= CARRY_FLAG;
+        } else {
+            self.p &= 0xFF - CARRY_FLAG;
+        }
+        self.set_zero_flag(new_val);
+        self.set_negative_flag(new_val);
+        // signed 8-bit overflow can only happen if both signs were positive but result was negative, or if both signs were negative and the result was positive
+        // sign is positive if num & 0x80 == 0, negative if num & 0x80 != 0
+        // ((sum & 0x80 != 0) && (acc & 0x80 == 0) && (operand & 0x80 == 0)) || ((sum & 0x80 == 0) && (acc & 0x80 != 0) && (operand & 0x80 != 0))
+        // simplifies to the below, thanks http://www.righto.com/2012/12/the-6502-overflow-flag-explained.html
+        if (byte ^ new_val) & (self.a ^ new_val) & 0x80 != 0 {
+            self.p |= OVERFLOW_FLAG;
+        } else {
+            self.p &= 0xFF - OVERFLOW_FLAG;
+        }
+        self.a = new_val; // actually change the accumulator
+    }
+
+    // SHA, SHX, SHY and TAS write value & (H + 1), where H is the high byte of the address before indexing.
+    // "If the addition of the index register crosses a page boundary, the high byte of the target address
+    // is replaced by the value being stored" (because the CPU is still putting the carry together).
+    pub fn unstable_store(&mut self, address: usize, index: u8, value: u8) {
+        let base = (address as u16).wrapping_sub(index as u16) as usize;
+        let value = value & ((base >> 8) as u8).wrapping_add(1);
+        let address = if base & 0xFF00 != address & 0xFF00 {
+            ((value as usize) << 8) | (address & 0xFF)
+        } else {
+            address
+        };
+        self.write(address, value);
     }
 
     pub fn compare(&mut self, reg: u8, byte: u8) {
@@ -69,8 +180,7 @@ impl super::Cpu {
 
     pub fn pop(&mut self) -> u8 {
         self.s = self.s.wrapping_add(1);
-        let byte = self.read(STACK_OFFSET + self.s as usize);
-        byte
+        self.read(STACK_OFFSET + self.s as usize)
     }
 
     pub fn push(&mut self, byte: u8) {
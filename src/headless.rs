@@ -0,0 +1,312 @@
+use super::console::{Frame, SCREEN_WIDTH, SCREEN_HEIGHT};
+use super::Console;
+
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+// Sample rate of the WAV files we write. The APU's raw output is averaged down to this.
+const WAV_SAMPLE_RATE: u32 = 44_100;
+const APU_SAMPLE_RATE: f64 = 894_886.5;
+
+// What to do during a headless run, which drives the console with no window or audio device.
+pub struct HeadlessOptions {
+    pub frames: usize,               // how many frames to run
+    pub screenshot: Option<PathBuf>, // PNG of the last frame
+    pub wav: Option<PathBuf>,        // all of the audio from the run
+    pub input: InputScript,          // controller input for each frame
+}
+
+pub fn run_headless(console: &mut Console, options: &HeadlessOptions) -> Result<(), String> {
+    let mut samples = vec![];
+    for frame_number in 0..options.frames {
+        console.step_frame(options.input.buttons_at(frame_number));
+        if options.wav.is_some() {
+            samples.append(&mut console.audio_samples());
+        }
+    }
+    if let Some(path) = &options.screenshot {
+        write_png(path, console.frame())?;
+        println!("screenshot written to {:?}", path);
+    }
+    if let Some(path) = &options.wav {
+        write_wav(path, &samples)?;
+        println!("audio written to {:?}", path);
+    }
+    Ok(())
+}
+
+// Scripted controller input, so that a run can be repeated exactly. Each line of the script is a frame number
+// followed by the buttons to hold down from that frame until the frame on the next line, for example:
+//     # frame  buttons
+//     30       start
+//     32
+//     100      right a
+// Button names are a, b, select, start, up, down, left, and right. Anything after a # is a comment.
+pub struct InputScript {
+    changes: Vec<(usize, u8)>, // (first frame, button states), sorted by frame
+}
+
+impl InputScript {
+    pub fn empty() -> Self {
+        InputScript { changes: vec![] }
+    }
+
+    pub fn load(path: &PathBuf) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read input script {:?}: {}", path, e))?;
+        InputScript::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut changes = vec![];
+        for (i, line) in text.lines().enumerate() {
+            let line = match line.find('#') {
+                Some(n) => &line[..n],
+                None => line,
+            };
+            let mut words = line.split_whitespace();
+            let frame = match words.next() {
+                Some(w) => w.parse::<usize>()
+                    .map_err(|_| format!("input script line {}: bad frame number {:?}", i + 1, w))?,
+                None => continue, // blank line
+            };
+            let mut button_states = 0;
+            for word in words {
+                button_states |= match word.to_lowercase().as_str() {
+                    "a"      => 1 << 0,
+                    "b"      => 1 << 1,
+                    "select" => 1 << 2,
+                    "start"  => 1 << 3,
+                    "up"     => 1 << 4,
+                    "down"   => 1 << 5,
+                    "left"   => 1 << 6,
+                    "right"  => 1 << 7,
+                    _ => return Err(format!("input script line {}: unknown button {:?}", i + 1, word)),
+                };
+            }
+            changes.push((frame, button_states));
+        }
+        changes.sort_by_key(|&(frame, _)| frame);
+        Ok(InputScript { changes })
+    }
+
+    pub fn buttons_at(&self, frame: usize) -> u8 {
+        match self.changes.iter().rev().find(|&&(start, _)| start <= frame) {
+            Some(&(_, button_states)) => button_states,
+            None => 0,
+        }
+    }
+}
+
+pub fn write_png(path: &PathBuf, frame: &Frame) -> Result<(), String> {
+    write_file(path, &encode_png(frame))
+}
+
+pub fn write_wav(path: &PathBuf, samples: &[f32]) -> Result<(), String> {
+    write_file(path, &encode_wav(samples))
+}
+
+// Encodes the frame as an 8-bit RGB PNG. The image data goes in uncompressed deflate blocks,
+// which keeps this short and means we don't need a compression library.
+fn encode_png(frame: &Frame) -> Vec<u8> {
+    // each scanline starts with a filter type byte, 0 for none
+    let mut raw = Vec::with_capacity(SCREEN_HEIGHT * (1 + SCREEN_WIDTH * 3));
+    for row in frame.pixels.chunks(SCREEN_WIDTH * 3) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+    // zlib stream: header, stored deflate blocks of at most 65535 bytes, then Adler-32 of the raw data
+    let mut zlib = vec![0x78, 0x01];
+    let num_blocks = raw.len().div_ceil(0xFFFF);
+    for (i, block) in raw.chunks(0xFFFF).enumerate() {
+        zlib.push(if i == num_blocks - 1 { 1 } else { 0 }); // BFINAL bit, BTYPE 00
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = vec![];
+    header.extend_from_slice(&(SCREEN_WIDTH as u32).to_be_bytes());
+    header.extend_from_slice(&(SCREEN_HEIGHT as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]); // bit depth 8, color type RGB, default compression/filter, no interlace
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    png_chunk(&mut png, b"IHDR", &header);
+    png_chunk(&mut png, b"IDAT", &zlib);
+    png_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32fast::hash(&png[start..]); // covers the chunk type and data, not the length
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Encodes the raw APU output as a 16-bit mono WAV, averaging each run of APU samples down to one output sample.
+fn encode_wav(samples: &[f32]) -> Vec<u8> {
+    let ratio = APU_SAMPLE_RATE / WAV_SAMPLE_RATE as f64;
+    let num_out = (samples.len() as f64 / ratio) as usize;
+    let mut pcm = Vec::with_capacity(num_out * 2);
+    for i in 0..num_out {
+        let start = (i as f64 * ratio) as usize;
+        let end = (((i + 1) as f64 * ratio) as usize).min(samples.len());
+        let window = &samples[start..end];
+        let average = window.iter().sum::<f32>() / window.len().max(1) as f32;
+        let value = (average.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        pcm.extend_from_slice(&value.to_le_bytes());
+    }
+    let mut wav = vec![];
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + pcm.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());                 // size of fmt chunk
+    wav.extend_from_slice(&1u16.to_le_bytes());                  // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes());                  // mono
+    wav.extend_from_slice(&WAV_SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(WAV_SAMPLE_RATE * 2).to_le_bytes()); // byte rate
+    wav.extend_from_slice(&2u16.to_le_bytes());                  // block align
+    wav.extend_from_slice(&16u16.to_le_bytes());                 // bits per sample
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(pcm.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&pcm);
+    wav
+}
+
+fn write_file(path: &PathBuf, data: &[u8]) -> Result<(), String> {
+    let mut f = File::create(path)
+        .map_err(|e| format!("could not create {:?}: {}", path, e))?;
+    f.write_all(data)
+        .map_err(|e| format!("could not write {:?}: {}", path, e))
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn input_script_holds_buttons_until_the_next_line() {
+        let script = InputScript::parse("# frame  buttons\n\n   \n30 start\n32\n100  Right a # jump\n10 select\n").unwrap();
+        assert_eq!(script.buttons_at(0), 0);
+        assert_eq!(script.buttons_at(10), 1 << 2);
+        assert_eq!(script.buttons_at(29), 1 << 2);
+        assert_eq!(script.buttons_at(30), 1 << 3);
+        assert_eq!(script.buttons_at(31), 1 << 3);
+        assert_eq!(script.buttons_at(32), 0);
+        assert_eq!(script.buttons_at(100), (1 << 7) | (1 << 0));
+        assert_eq!(script.buttons_at(1_000_000), (1 << 7) | (1 << 0));
+        assert_eq!(InputScript::empty().buttons_at(5), 0);
+        assert_eq!(InputScript::parse("").unwrap().buttons_at(0), 0);
+    }
+
+    #[test]
+    fn input_script_rejects_bad_lines() {
+        assert_eq!(InputScript::parse("0 a\n5 jump").err().unwrap(), "input script line 2: unknown button \"jump\"");
+        assert_eq!(InputScript::parse("a 5").err().unwrap(), "input script line 1: bad frame number \"a\"");
+        assert!(InputScript::parse("-1 a").is_err());
+    }
+
+    #[test]
+    fn png_has_valid_header_and_chunks() {
+        let mut frame = Frame::new();
+        frame.set_pixel(0, 0, [0xFF, 0x80, 0x01]);
+        let png = encode_png(&frame);
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+
+        // walk the chunks, checking each one's length and CRC
+        let mut chunks = vec![];
+        let mut pos = 8;
+        while pos < png.len() {
+            let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let kind = &png[pos + 4..pos + 8];
+            let data = &png[pos + 8..pos + 8 + len];
+            let crc = u32::from_be_bytes(png[pos + 8 + len..pos + 12 + len].try_into().unwrap());
+            assert_eq!(crc, crc32fast::hash(&png[pos + 4..pos + 8 + len]));
+            chunks.push((kind.to_vec(), data.to_vec()));
+            pos += 12 + len;
+        }
+        assert_eq!(pos, png.len());
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(kinds, vec![&b"IHDR"[..], &b"IDAT"[..], &b"IEND"[..]]);
+
+        let header = &chunks[0].1;
+        assert_eq!(header.len(), 13);
+        assert_eq!(u32::from_be_bytes(header[0..4].try_into().unwrap()), SCREEN_WIDTH as u32);
+        assert_eq!(u32::from_be_bytes(header[4..8].try_into().unwrap()), SCREEN_HEIGHT as u32);
+        assert_eq!(&header[8..], &[8, 2, 0, 0, 0]);
+
+        // undo the stored deflate blocks and check that the scanlines come back out
+        let zlib = &chunks[1].1;
+        assert_eq!(&zlib[..2], &[0x78, 0x01]);
+        let mut raw = vec![];
+        let mut pos = 2;
+        loop {
+            let last = zlib[pos] & 1 == 1;
+            let len = u16::from_le_bytes([zlib[pos + 1], zlib[pos + 2]]);
+            let nlen = u16::from_le_bytes([zlib[pos + 3], zlib[pos + 4]]);
+            assert_eq!(nlen, !len);
+            raw.extend_from_slice(&zlib[pos + 5..pos + 5 + len as usize]);
+            pos += 5 + len as usize;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(raw.len(), SCREEN_HEIGHT * (1 + SCREEN_WIDTH * 3));
+        assert_eq!(&raw[..4], &[0, 0xFF, 0x80, 0x01]);
+        assert_eq!(&zlib[pos..], &adler32(&raw).to_be_bytes());
+    }
+
+    #[test]
+    fn adler32_matches_known_value() {
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn wav_has_valid_header_and_downsampled_length() {
+        // one second of APU output should come out as one second at 44.1 kHz
+        let samples = vec![0.5f32; APU_SAMPLE_RATE as usize];
+        let wav = encode_wav(&samples);
+        let u32_at = |i: usize| u32::from_le_bytes(wav[i..i + 4].try_into().unwrap());
+        let u16_at = |i: usize| u16::from_le_bytes(wav[i..i + 2].try_into().unwrap());
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(u32_at(4) as usize, wav.len() - 8);
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32_at(16), 16);
+        assert_eq!(u16_at(20), 1);
+        assert_eq!(u16_at(22), 1);
+        assert_eq!(u32_at(24), WAV_SAMPLE_RATE);
+        assert_eq!(u32_at(28), WAV_SAMPLE_RATE * 2);
+        assert_eq!(u16_at(32), 2);
+        assert_eq!(u16_at(34), 16);
+        assert_eq!(&wav[36..40], b"data");
+        let data_len = u32_at(40) as usize;
+        assert_eq!(data_len, wav.len() - 44);
+        assert!((data_len / 2).abs_diff(WAV_SAMPLE_RATE as usize) <= 1);
+        assert_eq!(u16_at(44) as i16, (0.5 * i16::MAX as f32) as i16);
+
+        // out of range samples are clipped, and no samples is an empty data chunk
+        let clipped = encode_wav(&[4.0; 100]);
+        assert_eq!(i16::from_le_bytes([clipped[44], clipped[45]]), i16::MAX);
+        let empty = encode_wav(&[]);
+        assert_eq!(empty.len(), 44);
+        assert_eq!(u32::from_le_bytes(empty[40..44].try_into().unwrap()), 0);
+    }
+}
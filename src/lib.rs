@@ -0,0 +1,16 @@
+pub mod cpu;
+pub mod ppu;
+pub mod apu;
+pub mod cartridge;
+pub mod console;
+pub mod nes_bus;
+pub mod headless;
+pub mod power_on;
+pub mod state;
+pub mod test_rom;
+pub mod trace;
+
+pub use console::{Console, Frame};
+use cpu::Cpu;
+use ppu::Ppu;
+use apu::Apu;
@@ -1,277 +1,288 @@
-mod cpu;
-mod ppu;
-mod apu;
-mod cartridge;
+#[cfg(feature = "sdl")]
 mod input;
+#[cfg(feature = "sdl")]
 mod screen;
+#[cfg(feature = "sdl")]
 mod audio;
-mod state;
+#[cfg(feature = "sdl")]
+mod window;
 
-use cpu::Cpu;
-use ppu::Ppu;
-use apu::Apu;
-use cartridge::{check_signature, get_mapper};
-use input::poll_buttons;
-use screen::{init_window, draw_pixel, draw_to_window};
-use state::{save_state, load_state, find_next_filename, find_last_save_state};
+// Built without SDL, there's no window to play in, but run --headless, test, trace and info still work.
+#[cfg(not(feature = "sdl"))]
+mod window {
+    use nestur::cartridge::patch::PatchChoice;
+    use nestur::power_on::PowerOnOptions;
 
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
-use std::time::{Instant, Duration};
-
-use sdl2::Sdl;
-use sdl2::render::{Canvas, Texture};
-use sdl2::keyboard::Keycode;
-use sdl2::EventPump;
-use sdl2::event::Event;
-use sdl2::pixels::PixelFormatEnum;
-use sdl2::video::Window;
-use sdl2::messagebox::*;
+    pub fn play(_rom: Option<String>, _patch: PatchChoice, _power_on: PowerOnOptions) -> Result<(), String> {
+        Err("this nestur was built without the sdl feature, so it has no window; use nestur run --headless, test, trace or info".to_string())
+    }
+}
 
-// use cpuprofiler::PROFILER;
+use nestur::Console;
+use nestur::headless::{run_headless, HeadlessOptions, InputScript};
+use nestur::power_on::{PowerOnOptions, RamPattern};
+use nestur::test_rom::run_test_rom;
+use nestur::trace::{run_trace, TraceOptions};
+use nestur::cartridge::Cartridge;
+use nestur::cartridge::patch::{load_patched, PatchChoice};
 
-enum GameExitMode {
-    QuitApplication,
-    NewGame(String),
-    Reset,
-    Nothing,
-}
+use std::path::PathBuf;
 
 fn main() -> Result<(), String> {
-    // Set up screen
-    let sdl_context = sdl2::init()?;
-    let mut event_pump = sdl_context.event_pump()?;
-    let (mut canvas, texture_creator) = init_window(&sdl_context).expect("Could not create window");
-    let mut texture = texture_creator.create_texture_streaming(
-        PixelFormatEnum::RGB24, 256 as u32, 240 as u32)
-        .map_err(|e| e.to_string())?;
-    let mut screen_buffer = vec![0; 256 * 240 * 3]; // contains raw RGB data for the screen
-
     let argv = std::env::args().collect::<Vec<String>>();
-    let mut filename = if argv.len() > 1 {
-        argv[1].to_string()
-    } else {
-        show_simple_message_box(
-            MessageBoxFlag::INFORMATION, "Welcome to Nestur!", INSTRUCTIONS, canvas.window()
-        ).map_err(|e| e.to_string())?;
-        let name;
-        'waiting: loop {
-            for event in event_pump.poll_iter() {
-                match event {
-                    Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. }
-                        => return Ok(()),
-                    Event::DropFile{ filename: f, .. } => {
-                        match check_signature(&f) {
-                            Ok(()) => {
-                                name = f;
-                                break 'waiting;
-                            },
-                            Err(e) => println!("{}", e),
-                        }
-                    },
-                    _ => (),
-                }
-            }
-            std::thread::sleep(Duration::from_millis(100));
+    // nestur run [--headless] [--frames N] [--screenshot out.png] [--wav out.wav] [--input script.txt] [--patch hack.ips | --no-patch] [power-on options] rom.nes
+    if argv.len() > 1 && argv[1] == "test" {
+        return run_tests(&argv[2..]);
+    }
+    if argv.len() > 1 && argv[1] == "trace" {
+        return trace(&argv[2..]);
+    }
+    if argv.len() > 1 && argv[1] == "info" {
+        return info(&argv[2..]);
+    }
+    let mut power_on = PowerOnOptions::new();
+    let mut patch = PatchChoice::Found;
+    let rom_arg = if argv.len() > 1 && argv[1] == "run" {
+        let (rom, patch_arg, power_on_options, options) = parse_run_args(&argv[2..])?;
+        power_on = power_on_options;
+        patch = patch_arg;
+        if let Some(options) = options {
+            // no window or audio device, so this works without a display
+            let (rom_data, _) = load_patched(&rom, None, &patch).map_err(|e| format!("{}: {}", rom, e))?;
+            let mut console = Console::load_rom_with_options(&rom_data, power_on).map_err(|e| format!("{}: {}", rom, e))?;
+            return run_headless(&mut console, &options);
         }
-        name
+        Some(rom)
+    } else {
+        argv.get(1).cloned()
     };
-    loop {
-        let res = run_game(&sdl_context, &mut event_pump, &mut screen_buffer, &mut canvas, &mut texture, &filename);
-        match res {
-            Ok(Some(GameExitMode::Reset)) => (),
-            Ok(Some(GameExitMode::NewGame(next_file))) => filename = next_file,
-            Ok(None) | Ok(Some(GameExitMode::QuitApplication)) => return Ok(()),
-            Err(e) => return Err(e),
-            Ok(Some(GameExitMode::Nothing)) => panic!("shouldn't have returned exit mode Nothing to main()"),
+
+    window::play(rom_arg, patch, power_on)
+}
+
+// Returns the ROM filename, which patch to use, the power-on options, and options for a headless run
+// if --headless was passed.
+fn parse_run_args(args: &[String]) -> Result<(String, PatchChoice, PowerOnOptions, Option<HeadlessOptions>), String> {
+    let mut headless = false;
+    let mut patch = PatchChoice::Found;
+    let mut power_on = PowerOnOptions::new();
+    let mut options = HeadlessOptions {
+        frames: 60,
+        screenshot: None,
+        wav: None,
+        input: InputScript::empty(),
+    };
+    let mut rom = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if parse_power_on_arg(arg, &mut args, &mut power_on)? {
+            continue;
+        }
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--headless"   => headless = true,
+            "--frames"     => options.frames = value()?.parse().map_err(|_| "--frames needs a number".to_string())?,
+            "--screenshot" => options.screenshot = Some(PathBuf::from(value()?)),
+            "--wav"        => options.wav = Some(PathBuf::from(value()?)),
+            "--input"      => options.input = InputScript::load(&PathBuf::from(value()?))?,
+            "--patch"      => patch = PatchChoice::File(value()?.to_string()),
+            "--no-patch"   => patch = PatchChoice::Skip,
+            a if a.starts_with("--") => return Err(format!("unknown option {}\n{}\n{}", a, RUN_USAGE, POWER_ON_USAGE)),
+            a => rom = Some(a.to_string()),
         }
     }
+    let rom = rom.ok_or(format!("no ROM file given\n{}\n{}", RUN_USAGE, POWER_ON_USAGE))?;
+    Ok((rom, patch, power_on, if headless { Some(options) } else { None }))
 }
 
-fn run_game(
-        sdl_context: &Sdl,
-        event_pump: &mut EventPump,
-        screen_buffer: &mut Vec<u8>,
-        canvas: &mut Canvas<Window>,
-        texture: &mut Texture,
-        filename: &str
-    ) -> Result<Option<GameExitMode>, String> {
-
-    println!("loading game {}", filename);
-
-    // Set up audio
-    let mut temp_buffer = vec![]; // receives one sample each time the APU ticks. this is a staging buffer so we don't have to lock the mutex too much.
-    let apu_buffer = Arc::new(Mutex::new(Vec::<f32>::new())); // stays in this thread, receives raw samples between frames
-    let sdl_buffer = Arc::clone(&apu_buffer); // used in audio device's callback to select the samples it needs
-    let audio_device = audio::initialize(sdl_context, sdl_buffer).expect("Could not create audio device");
-    let mut half_cycle = false;
-    let mut audio_started = false;
+// Handles the options that set up the console's power-on state, which run and test share.
+// Returns false if arg isn't one of them.
+fn parse_power_on_arg(arg: &str, args: &mut std::slice::Iter<String>, power_on: &mut PowerOnOptions) -> Result<bool, String> {
+    let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+    match arg {
+        "--ram" => {
+            power_on.ram = RamPattern::parse(value()?)?;
+            if let RamPattern::Random(seed) = power_on.ram {
+                println!("filling RAM with random seed {}", seed); // so the run can be repeated
+            }
+        },
+        "--ppu-warmup" => power_on.ppu_warmup = true,
+        "--ppu-offset" => power_on.ppu_offset = match value()?.parse() {
+            Ok(n) if n < 3 => n,
+            _ => return Err("--ppu-offset needs a number from 0 to 2".to_string()),
+        },
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
 
-    // Initialize hardware components
-    let filepath = Path::new(filename).to_path_buf();
-    let mapper = get_mapper(filename.to_string());
-    let ppu = Ppu::new(mapper.clone());
-    let apu = Apu::new();
-    let mut cpu = Cpu::new(mapper.clone(), ppu, apu);
+const RUN_USAGE: &str = "usage: nestur run [--headless] [--frames N] [--screenshot out.png] [--wav out.wav] [--input script.txt] [--patch hack.ips | --no-patch] [power-on options] rom.nes
+    --headless    run without a window or audio device, for N frames (default 60)
+    --screenshot  with --headless, write the last frame to a PNG file
+    --wav         with --headless, write all of the audio to a WAV file
+    --input       with --headless, read controller input from a script (see headless.rs)
+    --patch       apply an IPS, UPS or BPS patch (by default, one with the same name as the ROM is used if it's there)
+    --no-patch    don't apply any patch, even one with the same name as the ROM";
 
-    // For throttling to 60 FPS
-    let mut timer = Instant::now();
-    let mut fps_timer = Instant::now();
-    let mut fps = 0;
-    let mut timer_counter = 0; // used to only check time every so many cycles
+const POWER_ON_USAGE: &str = "power-on options:
+    --ram zeros|ff|pattern|random|random:SEED   initial contents of the CPU's RAM (default zeros)
+    --ppu-warmup                                 the PPU ignores most register writes until the end of the first frame
+    --ppu-offset N                               run the PPU N dots (0-2) ahead of the CPU";
 
-    // PROFILER.lock().unwrap().start("./main.profile").unwrap();
-    'running: loop {
-        // step CPU: perform 1 cpu instruction, getting back number of clock cycles it took
-        let cpu_cycles = cpu.step();
-        // clock APU every other CPU cycle
-        let mut apu_cycles = cpu_cycles / 2;
-        if cpu_cycles & 1 == 1 {   // if cpu step took an odd number of cycles
-            if half_cycle {        // and we have a half-cycle stored
-                apu_cycles += 1;   // use it
-                half_cycle = false;
-            } else {
-                half_cycle = true; // or save it for next odd cpu step
-            }
-        }
-        for _ in 0..apu_cycles {
-            // can't read CPU from APU so have to pass byte in here
-            let sample_byte = cpu.read(cpu.apu.dmc.current_address);
-            temp_buffer.push(cpu.apu.clock(sample_byte));
+// nestur test [--frames N] [power-on options] rom.nes...
+// Runs each test ROM headlessly and prints its result. Fails if any of them didn't pass.
+fn run_tests(args: &[String]) -> Result<(), String> {
+    let mut max_frames = 60 * 60;
+    let mut power_on = PowerOnOptions::new();
+    let mut roms = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if parse_power_on_arg(arg, &mut args, &mut power_on)? {
+            continue;
         }
-        // clock PPU three times for every CPU cycle
-        for _ in 0..cpu_cycles * 3 {
-            let (pixel, end_of_frame) = cpu.ppu.clock();
-            match pixel {
-                Some((x, y, color)) => draw_pixel(screen_buffer, x, y, color),
-                None => (),
-            };
-            if end_of_frame {
-                fps += 1; // keep track of how many frames we've rendered this second
-                draw_to_window(texture, canvas, &screen_buffer)?; // draw the buffer to the window with SDL
-                let mut b = apu_buffer.lock().unwrap(); // unlock mutex to the real buffer
-                b.append(&mut temp_buffer); // send this frame's audio data, emptying the temp buffer
-                if !audio_started {
-                    audio_started = true;
-                    audio_device.resume();
-                }
-                let now = Instant::now();
-                // if we're running faster than 60Hz, kill time
-                if now < timer + Duration::from_millis(1000/60) {
-                    std::thread::sleep(timer + Duration::from_millis(1000/60) - now);
-                }
-                timer = Instant::now();
-                let outcome = process_events(event_pump, &filepath, &mut cpu);
-                match outcome {
-                    GameExitMode::QuitApplication => break 'running,
-                    GameExitMode::Reset => return Ok(Some(GameExitMode::Reset)),
-                    GameExitMode::NewGame(g) => return Ok(Some(GameExitMode::NewGame(g))),
-                    GameExitMode::Nothing => (),
-                }
-            }
-        }
-        // handle keyboard events
-        match poll_buttons(&cpu.strobe, &event_pump) {
-            Some(button_states) => cpu.button_states = button_states,
-            None => (),
-        };
-        // calculate fps
-        if timer_counter == 999 {
-            let now = Instant::now();
-            if now > fps_timer + Duration::from_secs(1) {
-                println!("frames per second: {}", fps);
-                fps = 0;
-                fps_timer = now;
-            }
-            timer_counter = 0;
-        } else {
-            timer_counter += 1;
+        match arg.as_str() {
+            "--frames" => max_frames = args.next().and_then(|n| n.parse().ok())
+                .ok_or("--frames needs a number".to_string())?,
+            r => roms.push(r.to_string()),
         }
     }
-    // PROFILER.lock().unwrap().stop().unwrap();
-    mapper.borrow().save_battery_backed_ram();
-    Ok(None)
-}
-
-fn process_events(event_pump: &mut EventPump, filepath: &PathBuf, cpu: &mut Cpu) -> GameExitMode {
-    for event in event_pump.poll_iter() {
-        match event {
-            Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. }
-                => return GameExitMode::QuitApplication,
-            Event::KeyDown{ keycode: Some(Keycode::F2), .. }
-                => return GameExitMode::Reset,
-            Event::KeyDown{ keycode: Some(Keycode::F5), .. } => {
-                let save_file = find_next_filename(filepath, Some("dat"))
-                    .expect("could not generate save state filename");
-                let res: Result<(), String> = save_state(cpu, &save_file)
-                    .or_else(|e| {println!("{}", e); Ok(())});
-                res.unwrap();
+    if roms.is_empty() {
+        return Err(format!("usage: nestur test [--frames N] [power-on options] rom.nes...\n{}", POWER_ON_USAGE));
+    }
+    let mut failures = 0;
+    for rom in roms.iter() {
+        let mut console = match load_patched(rom, None, &PatchChoice::Found).and_then(|(rom_data, _)| Console::load_rom_with_options(&rom_data, power_on)) {
+            Ok(c) => c,
+            Err(e) => {
+                failures += 1;
+                println!("FAIL {}: {}", rom, e);
+                continue;
             },
-            Event::KeyDown{ keycode: Some(Keycode::F9), .. } => {
-                match find_last_save_state(filepath, Some("dat")) {
-                    Some(p) => {
-                        let res: Result<(), String> = load_state(cpu, &p)
-                            .or_else(|e| { println!("{}", e); Ok(()) } );
-                        res.unwrap();
-                    },
-                    None => println!("no save state found for {:?}", filepath)
-                }
+        };
+        match run_test_rom(&mut console, max_frames) {
+            Ok(result) if result.passed() => println!("PASS {}", rom),
+            Ok(result) => {
+                failures += 1;
+                println!("FAIL {} (code {}): {}", rom, result.code, result.message.trim());
             },
-            Event::DropFile{ timestamp: _t, window_id: _w, filename: f } => {
-                if f.len() > 4 && &f[f.len()-4..] == ".dat" {
-                    let p = Path::new(&f).to_path_buf();
-                    let res: Result<(), String> = load_state(cpu, &p)
-                        .or_else(|e| {println!("{}", e); Ok(())});
-                    res.unwrap();
-                // } else if f.len() > 4 && &f[f.len()-4..] == ".nes" {
-                } else {
-                    match check_signature(&f) {
-                        Ok(()) => return GameExitMode::NewGame(f),
-                        Err(e) => println!("{}", e),
-                    }
-                }
+            Err(e) => {
+                failures += 1;
+                println!("FAIL {}: {}", rom, e);
             },
-            _ => (),
         }
     }
-    return GameExitMode::Nothing
+    match failures {
+        0 => Ok(()),
+        n => Err(format!("{} of {} test ROMs failed", n, roms.len())),
+    }
 }
 
-const INSTRUCTIONS: &str = "To play a game, drag an INES file (extension .nes) onto the main window.
-To save the game state, press F5. To load the most recent save state, press F9.
-To load another save state file, drag a .dat file onto the window while the game is running.
-Battery-backed RAM saves (what the NES cartridges have) will be written to a .sav file if used.
-To reset the console/current game, press F2.
+// nestur trace [--log out.txt] [--compare nestest.log] [--start-pc C000] [--instructions N] rom.nes
+// Writes a nestest.log-style line for each instruction, and optionally stops at the first difference from a reference log.
+fn trace(args: &[String]) -> Result<(), String> {
+    let usage = "usage: nestur trace [--log out.txt] [--compare reference.log] [--start-pc C000] [--instructions N] rom.nes";
+    let mut options = TraceOptions {
+        output: None,
+        reference: None,
+        start_pc: None,
+        max_instructions: usize::MAX,
+    };
+    let mut rom = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--log"          => options.output = Some(PathBuf::from(value()?)),
+            "--compare"      => options.reference = Some(PathBuf::from(value()?)),
+            "--start-pc"     => options.start_pc = Some(usize::from_str_radix(value()?.trim_start_matches('$'), 16)
+                .map_err(|_| "--start-pc needs a hex address".to_string())?),
+            "--instructions" => options.max_instructions = value()?.parse()
+                .map_err(|_| "--instructions needs a number".to_string())?,
+            a if a.starts_with("--") => return Err(format!("unknown option {}\n{}", a, usage)),
+            a => rom = Some(a.to_string()),
+        }
+    }
+    let rom = rom.ok_or(usage.to_string())?;
+    if options.reference.is_none() && options.max_instructions == usize::MAX {
+        options.max_instructions = 10_000;
+    }
+    let (rom_data, _) = load_patched(&rom, None, &PatchChoice::Found).map_err(|e| format!("{}: {}", rom, e))?;
+    let mut console = Console::load_rom(&rom_data).map_err(|e| format!("{}: {}", rom, e))?;
+    run_trace(&mut console, &options)
+}
 
-Controls
-------------
-A: D
-B: F
-Start: enter
-Select: (right) shift
-Up/Down/Left/Right: arrow keys
-";
+// nestur info [--json] [--patch hack.ips | --no-patch] rom.nes
+// Prints what's in a ROM's header and whether nestur supports its mapper.
+fn info(args: &[String]) -> Result<(), String> {
+    let usage = "usage: nestur info [--json] [--patch hack.ips | --no-patch] rom.nes";
+    let mut json = false;
+    let mut patch = PatchChoice::Found;
+    let mut rom = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--patch" => patch = PatchChoice::File(args.next().ok_or("--patch needs a value".to_string())?.to_string()),
+            "--no-patch" => patch = PatchChoice::Skip,
+            a if a.starts_with("--") => return Err(format!("unknown option {}\n{}", a, usage)),
+            a => rom = Some(a.to_string()),
+        }
+    }
+    let rom = rom.ok_or(usage.to_string())?;
+    let cart = Cartridge::new_patched(rom.clone(), &patch).map_err(|e| format!("{}: {}", rom, e))?;
+    let info = cart.info();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info).map_err(|e| e.to_string())?);
+        return Ok(());
+    }
+    let yes_no = |b| if b { "yes" } else { "no" };
+    let size = |bytes: usize| match bytes {
+        0 => "none".to_string(),
+        b if b % 1024 == 0 => format!("{} KiB", b / 1024),
+        b => format!("{} bytes", b),
+    };
+    println!("file:         {}", rom);
+    println!("format:       {}", info.format);
+    println!("mapper:       {} ({}){}", info.mapper, info.mapper_name,
+        if info.supported { "" } else { ", not supported by nestur" });
+    match info.submapper_name {
+        Some(ref name) => println!("submapper:    {} ({})", info.submapper, name),
+        None => println!("submapper:    {}", info.submapper),
+    }
+    println!("PRG-ROM:      {}", size(info.prg_rom));
+    println!("CHR-ROM:      {}", size(info.chr_rom));
+    println!("PRG-RAM:      {}", size(info.prg_ram));
+    println!("PRG-NVRAM:    {}", size(info.prg_nvram));
+    println!("CHR-RAM:      {}", size(info.chr_ram));
+    println!("CHR-NVRAM:    {}", size(info.chr_nvram));
+    println!("mirroring:    {:?}", info.mirroring);
+    println!("battery:      {}", yes_no(info.battery));
+    println!("trainer:      {}", yes_no(info.trainer));
+    println!("four-screen:  {}", yes_no(info.four_screen));
+    println!("region:       {:?}", info.region);
+    println!("console:      {:?}", info.console_type);
+    println!("expansion:    {}", info.expansion_device);
+    println!("CRC32:        {}", info.crc32);
+    println!("SHA-1:        {}", info.sha1);
+    for fix in info.database_fixes.iter() {
+        println!("fixed by database: {}", fix);
+    }
+    Ok(())
+}
 
 /*
 
 TODO:
-- untangle CPU and APU/PPU?
 - better save file organization?
 
+The emulator itself lives in the library crate (see Console in console.rs), which has no SDL dependency.
+This binary is just one frontend for it: the window, audio device, keyboard, and save state hotkeys are in window.rs,
+which is left out when it's built without the sdl feature.
+
 
 Timing notes:
 The PPU is throttled to 60Hz by sleeping in the main loop. This locks the CPU to roughly its intended speed, 1.789773MHz NTSC. The APU runs at half that.
 The APU gives all of its samples to the SDL audio device, which takes them 60 times per second in batches of 735 (44,100/60). It selects the ones
 it needs at the proper interval and truncates its buffer.
 
-Failed tests from instr_test-v5/rom_singles/:
-3, immediate, Failed. Just unofficial instructions?
-    0B AAC #n
-    2B AAC #n
-    4B ASR #n
-    6B ARR #n
-    AB ATX #n
-    CB AXS #n
-7, abs_xy, 'illegal opcode using abs x: 9c'
-
 */
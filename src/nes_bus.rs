@@ -0,0 +1,250 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use serde::{Serialize, Deserialize};
+use crate::cartridge::Mapper;
+use crate::console::Frame;
+use crate::cpu::bus::Bus;
+use crate::{Ppu, Apu};
+
+// The NES as the CPU sees it: RAM, the PPU and APU registers, the controller port and the cartridge,
+// with the PPU and APU running a cycle at a time alongside the CPU.
+pub struct NesBus {
+    mem: Vec<u8>, // CPU's 2KB of RAM, mirrored through $0000-$1FFF
+    data_bus: u8, // last value on the data bus, which is what reads of unmapped addresses see ("open bus")
+    cycles: u64,  // CPU cycles since power-on. The APU runs on every other one.
+    oam_dma_page: Option<u8>, // written to $4014, waiting for the DMA unit to pick it up
+
+    pub mapper: Rc<RefCell<dyn Mapper>>, // cartridge data
+    pub ppu: Ppu,
+    pub apu: Apu,
+
+    // The PPU and APU's output collects here.
+    pub frame: Frame,           // pixels drawn by the PPU
+    pub frame_done: bool,       // set when the PPU finishes a frame. Cleared by whoever is consuming frames.
+    pub audio_buffer: Vec<f32>, // receives one sample each time the APU ticks
+
+    // controller
+    pub strobe: u8, // signals to the controller that button inputs should be read
+    pub button_states: u8, // Player 1 controller
+    button_number: u8, // counter that scans the bits of the input register serially
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BusData {
+    mem: Vec<u8>,
+    data_bus: u8,
+    cycles: u64,
+    oam_dma_page: Option<u8>,
+    strobe: u8,
+    button_states: u8,
+    button_number: u8,
+}
+
+impl NesBus {
+    // ram is the 2KB of internal RAM as it is at power-on
+    pub fn new(mapper: Rc<RefCell<dyn Mapper>>, ppu: Ppu, apu: Apu, ram: Vec<u8>) -> Self {
+        NesBus {
+            mem: ram,
+            data_bus: 0,
+            cycles: 0,
+            oam_dma_page: None,
+            mapper,
+            ppu,
+            apu,
+            frame: Frame::new(),
+            frame_done: false,
+            audio_buffer: vec![],
+            strobe: 0,
+            button_states: 0,
+            button_number: 0,
+        }
+    }
+
+    fn read_controller(&mut self) -> u8 {
+        let bit = match self.button_number < 8 {
+            true => (self.button_states & (1<<self.button_number) != 0) as u8,
+            false => 1,
+        };
+        if self.strobe & 1 != 0 {
+            self.button_number = 0;
+        } else if self.button_number < 8 {
+            self.button_number += 1;
+        }
+        // the controller only drives the low bits; the top three are open bus
+        (self.data_bus & 0xE0) | bit
+    }
+
+    fn write_controller(&mut self, val: u8) {
+        self.strobe = val;
+        if self.strobe & 1 != 0 {
+            self.button_number = 0;
+        }
+    }
+
+    fn read_ppu_reg(&mut self, reg_num: usize) -> u8 {
+        match reg_num {
+            2 => self.ppu.read_status(),
+            4 => self.ppu.read_oam_data(),
+            7 => self.ppu.read_data(),
+            _ => self.ppu.recent_bits,
+        }
+    }
+
+    fn write_ppu_reg(&mut self, reg_num: usize, val: u8) {
+        self.ppu.recent_bits = val;
+        match reg_num {
+            0 | 1 | 5 | 6 if self.ppu.warming_up => (),
+            0 => self.ppu.write_controller(val),
+            1 => self.ppu.write_mask(val),
+            3 => self.ppu.write_oam_address(val as usize),
+            4 => self.ppu.write_oam_data(val),
+            5 => self.ppu.write_scroll(val),
+            6 => self.ppu.write_address(val),
+            7 => self.ppu.write_data(val),
+            8 => self.oam_dma_page = Some(val),
+            _ => panic!("wrote to bad ppu reg: {}", reg_num),
+        }
+    }
+
+    pub fn _memory_at(&mut self, address: usize, amount: usize) -> Vec<u8> {
+        let mut ret = vec![];
+        for i in 0..amount {
+            ret.push(self.read(address+i));
+        }
+        ret
+    }
+
+    pub fn save_state(&self) -> BusData {
+        BusData {
+            mem: self.mem.clone(),
+            data_bus: self.data_bus,
+            cycles: self.cycles,
+            oam_dma_page: self.oam_dma_page,
+            strobe: self.strobe,
+            button_states: self.button_states,
+            button_number: self.button_number,
+        }
+    }
+
+    pub fn load_state(&mut self, data: BusData) {
+        self.mem = data.mem;
+        self.data_bus = data.data_bus;
+        self.cycles = data.cycles;
+        self.oam_dma_page = data.oam_dma_page;
+        self.strobe = data.strobe;
+        self.button_states = data.button_states;
+        self.button_number = data.button_number;
+    }
+}
+
+impl Bus for NesBus {
+    // Nothing drives the data bus on a read of an unmapped address, so the CPU sees whatever was last on it,
+    // which is usually the high byte of the address it just read from its operand.
+    fn read(&mut self, address: usize) -> u8 {
+        let val = match address {
+            0x0000..=0x1FFF => self.mem[address % 0x0800],
+            0x2000..=0x3FFF => self.read_ppu_reg(address % 8),
+            0x4015          => {
+                // $4015 is inside the CPU, so reading it doesn't change the external bus, and bit 5 is left open
+                let status = self.apu.read_status();
+                return (status & 0xDF) | (self.data_bus & 0x20);
+            },
+            0x4016          => self.read_controller(),
+            0x4000..=0x4017 => self.data_bus, // write-only APU and I/O registers. No second controller.
+            0x4018..=0x401F => self.data_bus, // APU and I/O functionality that is normally disabled. See CPU Test Mode.
+            0x4020..=0xFFFF => {
+                let val = self.mapper.borrow().read(address).unwrap_or(self.data_bus);
+                self.mapper.borrow_mut().cpu_read(address);
+                val
+            },
+            _ => panic!("invalid read from 0x{:02x}", address),
+        };
+        self.data_bus = val;
+        val
+    }
+
+    fn write(&mut self, address: usize, val: u8) {
+        self.data_bus = val;
+        match address {
+            0x0000..=0x1FFF => self.mem[address % 0x0800] = val,
+            0x2000..=0x3FFF => self.write_ppu_reg(address % 8, val),
+            0x4014          => self.write_ppu_reg(8, val),
+            0x4016          => self.write_controller(val),
+            0x4000..=0x4017 => self.apu.write_reg(address, val),
+            0x4018..=0x401F => (), // APU and I/O functionality that is normally disabled. See CPU Test Mode.
+            0x4020..=0xFFFF => self.mapper.borrow_mut().write(address, val),
+            _ => panic!("invalid write to {:02x}", address),
+        }
+    }
+
+    fn tick(&mut self) {
+        self.cycles += 1;
+        // clock APU every other CPU cycle
+        if self.cycles & 1 == 0 {
+            let sample = self.apu.clock() + self.mapper.borrow_mut().expansion_audio();
+            self.audio_buffer.push(sample);
+        }
+        // clock PPU three times for every CPU cycle
+        for _ in 0..3 {
+            let (pixel, end_of_frame) = self.ppu.clock();
+            if let Some((x, y, color)) = pixel {
+                self.frame.set_pixel(x, y, color);
+            }
+            self.frame_done |= end_of_frame;
+        }
+    }
+
+    // "The NMI input is edge-sensitive ... The IRQ input is level-sensitive" (https://wiki.nesdev.com/w/index.php/CPU_interrupts)
+    fn nmi(&mut self) -> bool {
+        let nmi = self.ppu.trigger_nmi;
+        self.ppu.trigger_nmi = false;
+        nmi
+    }
+
+    // Everything that can pull the shared IRQ line low: the APU frame counter, the DMC, and the cartridge.
+    fn irq(&self) -> bool {
+        self.apu.irq_asserted() || self.mapper.borrow().irq_asserted()
+    }
+
+    fn oam_dma_request(&mut self) -> Option<u8> {
+        self.oam_dma_page.take()
+    }
+
+    // the DMC gets its sample bytes from memory through DMA
+    fn dmc_dma_request(&self) -> Option<usize> {
+        match self.apu.dmc.dma_request {
+            true => Some(self.apu.dmc.current_address),
+            false => None,
+        }
+    }
+
+    fn dmc_dma_done(&mut self, value: u8) {
+        self.apu.dmc.load_sample(value);
+    }
+
+    // Returns None for registers that would be changed by reading them.
+    fn peek(&self, address: usize) -> Option<u8> {
+        match address {
+            0x0000..=0x1FFF => Some(self.mem[address % 0x0800]),
+            0x4020..=0xFFFF => self.mapper.borrow().read(address),
+            _ => None,
+        }
+    }
+
+    fn ppu_position(&self) -> Option<(usize, usize)> {
+        Some(self.ppu.position())
+    }
+}
+
+/*
+Address range 	Size 	Device
+$0000-$07FF 	$0800 	2KB internal RAM
+$0800-$0FFF 	$0800 	]---+
+$1000-$17FF 	$0800       |---- Mirrors of $0000-$07FF
+$1800-$1FFF 	$0800   ]---+
+$2000-$2007 	$0008 	NES PPU registers
+$2008-$3FFF 	$1FF8 	Mirrors of $2000-2007 (repeats every 8 bytes)
+$4000-$4017 	$0018 	NES APU and I/O registers
+$4018-$401F 	$0008 	APU and I/O functionality that is normally disabled. See CPU Test Mode.
+$4020-$FFFF 	$BFE0 	Cartridge space: PRG ROM, PRG RAM, and mapper registers (See Note)
+*/
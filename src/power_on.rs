@@ -0,0 +1,66 @@
+// What the console looks like the moment it's turned on. Real hardware isn't consistent about this, and games that
+// work on one console and not another usually depend on it by accident, so these let us try the different possibilities.
+// See https://wiki.nesdev.com/w/index.php/CPU_power_up_state and https://wiki.nesdev.com/w/index.php/PPU_power_up_state
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RamPattern {
+    Zeros,
+    Ones,         // every byte $FF
+    Random(u64),  // pseudorandom bytes from a seed, so a run can be repeated
+    Alternating,  // four bytes of $00, then four of $FF, and so on, which is common on real consoles
+}
+
+impl RamPattern {
+    // Parses "zeros", "ff", "pattern", "random" or "random:SEED". Plain "random" picks a seed from the clock.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "zeros" => Ok(RamPattern::Zeros),
+            "ff" => Ok(RamPattern::Ones),
+            "pattern" => Ok(RamPattern::Alternating),
+            "random" => {
+                let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+                Ok(RamPattern::Random(now.as_nanos() as u64))
+            },
+            _ => match s.strip_prefix("random:") {
+                Some(seed) => seed.parse().map(RamPattern::Random).map_err(|_| format!("bad random seed: {}", seed)),
+                None => Err(format!("unknown RAM pattern {} (expected zeros, ff, pattern, random or random:SEED)", s)),
+            },
+        }
+    }
+
+    pub fn fill(&self, size: usize) -> Vec<u8> {
+        match *self {
+            RamPattern::Zeros => vec![0; size],
+            RamPattern::Ones => vec![0xFF; size],
+            RamPattern::Alternating => (0..size).map(|i| if i & 4 == 0 {0x00} else {0xFF}).collect(),
+            RamPattern::Random(seed) => {
+                // xorshift64*, which is plenty for this. The state can't be 0.
+                let mut state = seed | 1;
+                (0..size).map(|_| {
+                    state ^= state >> 12;
+                    state ^= state << 25;
+                    state ^= state >> 27;
+                    (state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+                }).collect()
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PowerOnOptions {
+    pub ram: RamPattern,   // initial contents of the CPU's 2KB of RAM
+    pub ppu_warmup: bool,  // the PPU ignores writes to $2000, $2001, $2005 and $2006 until the end of the first vblank
+    pub ppu_offset: usize, // PPU dots (0-2) run before the CPU's first cycle, which decides where in each CPU cycle the PPU's events land
+}
+
+impl PowerOnOptions {
+    // How the emulator has always started up: zeroed RAM, a PPU that's ready right away, and no offset.
+    pub fn new() -> Self {
+        PowerOnOptions {
+            ram: RamPattern::Zeros,
+            ppu_warmup: false,
+            ppu_offset: 0,
+        }
+    }
+}
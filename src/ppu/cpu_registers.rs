@@ -64,9 +64,9 @@ impl super::Ppu {
 
     // cpu writes to 0x2004, OAMDATA
     pub fn write_oam_data(&mut self, val: u8) {
-        // Writes will increment OAMADDR after the write
+        // Writes will increment OAMADDR after the write, wrapping around at the end of OAM
         self.primary_oam[self.oam_address] = val;
-        self.oam_address += 1;
+        self.oam_address = (self.oam_address + 1) & 0xFF;
     }
 
     // cpu writes to 0x2005, PPUSCROLL
@@ -196,12 +196,6 @@ impl super::Ppu {
             self.v += self.address_increment;
         }
     }
-
-    // cpu writes to 0x4014, OAMDATA
-    pub fn write_oam_dma(&mut self, data: Vec<u8>) {
-        self.primary_oam = data;
-    }
-
 }
 
 pub fn set_bit(dest: &mut u16, dest_pos: usize, src: u16, src_pos: usize) {
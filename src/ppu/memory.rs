@@ -4,13 +4,14 @@ impl super::Ppu {
 
     pub fn read(&mut self, address: usize) -> u8 {
         match address {
-            0x0000..=0x1FFF => self.mapper.borrow().read(address),
-            0x2000..=0x3EFF => self.read_nametable(address),
-            0x3F00..=0x3FFF => {
-                let a = address % 0x0020;
-                let value = self.palette_ram[a];
+            0x0000..=0x1FFF => {
+                let value = self.mapper.borrow().read(address).unwrap_or(0);
+                // let MMC2/MMC4 see which tiles are fetched, after the fetch since a latch only switches banks for the next one
+                self.mapper.borrow_mut().ppu_read(address);
                 value
             },
+            0x2000..=0x3EFF => self.read_nametable(address),
+            0x3F00..=0x3FFF => self.palette_ram[address % 0x0020],
             _ => 0,
         }
     }
@@ -51,11 +52,18 @@ impl super::Ppu {
     }
 
     fn read_nametable(&mut self, address: usize) -> u8 {
+        if let Some(value) = self.mapper.borrow_mut().read_nametable(address) {
+            return value
+        }
         let base = address % 0x1000;
         let offset = base % 0x0400;
         match self.mapper.borrow().get_mirroring() {
             Mirror::LowBank => self.nametable_a[offset],
             Mirror::HighBank => self.nametable_b[offset],
+            Mirror::Custom(pages) => match pages[base / 0x0400] {
+                0 => self.nametable_a[offset],
+                _ => self.nametable_b[offset],
+            },
             Mirror::Horizontal => {
                 match base {
                     0x0000..=0x07FF => self.nametable_a[offset],
@@ -83,11 +91,18 @@ impl super::Ppu {
     }
 
     fn write_nametable(&mut self, address: usize, value: u8) {
+        if self.mapper.borrow_mut().write_nametable(address, value) {
+            return
+        }
         let base = address % 0x1000;
         let offset = base % 0x0400;
         match self.mapper.borrow().get_mirroring() {
             Mirror::LowBank => self.nametable_a[offset] = value,
             Mirror::HighBank => self.nametable_b[offset] = value,
+            Mirror::Custom(pages) => match pages[base / 0x0400] {
+                0 => self.nametable_a[offset] = value,
+                _ => self.nametable_b[offset] = value,
+            },
             Mirror::Horizontal => {
                 match base {
                     0x0000..=0x07FF => self.nametable_a[offset] = value,
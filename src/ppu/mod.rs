@@ -89,6 +89,8 @@ pub struct Ppu {
 
     read_buffer:                   u8,   // used with PPUDATA register
     pub recent_bits:               u8,   // Least significant bits previously written into a PPU register
+    pub warming_up:                bool, // after power-on or reset, PPUCTRL, PPUMASK, PPUSCROLL and PPUADDR ignore writes until the end of the first vblank
+    pub warmup:                    bool, // whether warming_up is set by power-on and reset, from the console's power-on options
 
     previous_a12:                  u8,
 }
@@ -103,7 +105,7 @@ impl Ppu {
             t:                             0,
             x:                             0,
             w:                             0,
-            mapper:                        mapper,
+            mapper,
             nametable_a:                   vec![0u8; 0x0400],
             nametable_b:                   vec![0u8; 0x0400],
             nametable_c:                   vec![0u8; 0x0400],
@@ -147,10 +149,31 @@ impl Ppu {
             nmi_delay:                     0,
             read_buffer:                   0,
             recent_bits:                   0,
+            warming_up:                    false,
+            warmup:                        false,
             previous_a12:                  0,
         }
     }
 
+    // (scanline, dot) that the PPU will render next
+    pub fn position(&self) -> (usize, usize) {
+        (self.scanline, self.line_cycle)
+    }
+
+    // The reset button clears PPUCTRL, PPUMASK, the scroll and the PPUDATA read buffer, and starts on an even frame.
+    // With the warm-up modeled, writes to those registers are ignored again until the end of the next vblank.
+    // OAM, palettes, nametables, PPUSTATUS and PPUADDR are left alone. See https://wiki.nesdev.com/w/index.php/PPU_power_up_state
+    pub fn reset(&mut self) {
+        self.write_controller(0);
+        self.write_mask(0);
+        self.w = 0;
+        self.t = 0;
+        self.x = 0;
+        self.read_buffer = 0;
+        self.frame = 0;
+        self.warming_up = self.warmup;
+    }
+
     pub fn clock(&mut self) -> (Option<(usize, usize, [u8; 3])>, bool) {
         if self.nmi_delay > 0 {
             self.nmi_delay -= 1;
@@ -161,6 +184,7 @@ impl Ppu {
 
         let mut pixel: Option<(usize, usize, [u8; 3])> = None;
         let rendering = self.rendering();
+        self.mapper.borrow_mut().ppu_tick(self.scanline, self.line_cycle, rendering, self.sprite_size == 16);
 
         // Visible scanlines (0-239)
         if rendering && (self.scanline < 240 || self.scanline == 261) {
@@ -217,6 +241,7 @@ impl Ppu {
             self.nmi_change();
         }
         if self.scanline == 261 && self.line_cycle == 1 {
+            self.warming_up = false;
             self.vertical_blank = false;
             self.nmi_change();
             self.sprite_zero_hit = false;
@@ -228,7 +253,7 @@ impl Ppu {
 
         // advance clock
         // For odd frames, the cycle at the end of the pre-render scanline is skipped
-        if self.line_cycle == 339 && self.scanline == 261 && self.frame % 2 != 0 {
+        if self.line_cycle == 339 && self.scanline == 261 && self.frame & 1 != 0 {
             self.line_cycle = 0;
             self.scanline = 0;
             self.frame = self.frame.wrapping_add(1);
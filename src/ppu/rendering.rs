@@ -229,7 +229,7 @@ impl super::Ppu {
             // For 8x16 sprites, the PPU ignores the pattern table selection and selects a pattern table from bit 0 of this number.
             } else {
                 address = if sprite_tile_index & 1 == 0 { 0x0 } else { 0x1000 };
-                address += (sprite_tile_index & 0xFFFF-1) << 4; // turn off bottom bit BEFORE shifting
+                address += (sprite_tile_index & (0xFFFF-1)) << 4; // turn off bottom bit BEFORE shifting
                 let fine_y = if !flipped_vertically {
                     self.scanline - sprite_y_position
                 } else {
@@ -52,6 +52,7 @@ pub struct PpuData {
     nmi_delay: usize,
     read_buffer: u8,
     recent_bits: u8,
+    warming_up: bool,
     previous_a12: u8,
 }
 
@@ -108,6 +109,7 @@ impl super::Ppu {
             nmi_delay: self.nmi_delay,
             read_buffer: self.read_buffer,
             recent_bits: self.recent_bits,
+            warming_up: self.warming_up,
             previous_a12: self.previous_a12,
         }
     }
@@ -163,6 +165,7 @@ impl super::Ppu {
         self.nmi_delay = data.nmi_delay;
         self.read_buffer = data.read_buffer;
         self.recent_bits = data.recent_bits;
+        self.warming_up = data.warming_up;
         self.previous_a12 = data.previous_a12;
     }
 }
@@ -7,7 +7,6 @@ use sdl2::video::{Window, WindowContext};
 
 pub const SCALE_FACTOR: usize = 2;
 
-type RGBColor = [u8; 3];
 
 pub fn init_window(context: &Sdl) -> Result<(Canvas<Window>, TextureCreator<WindowContext>), String> {
     let video_subsystem = context.video()?;
@@ -26,15 +25,10 @@ pub fn init_window(context: &Sdl) -> Result<(Canvas<Window>, TextureCreator<Wind
     Ok((canvas, texture_creator))
 }
 
-pub fn draw_pixel(buffer: &mut Vec<u8>, x: usize, y: usize, color: RGBColor) {
-    let offset = (y * 3 * 256) + (x * 3);
-    buffer.splice(offset..(offset+3), color);
-}
-
-pub fn draw_to_window(texture: &mut Texture, canvas: &mut Canvas<Window>, buffer: &Vec<u8>) -> Result<(), String> {
-    texture.update(None, &buffer, 256*3)
+pub fn draw_to_window(texture: &mut Texture, canvas: &mut Canvas<Window>, buffer: &[u8]) -> Result<(), String> {
+    texture.update(None, buffer, 256*3)
         .map_err(|e| e.to_string())?;
-    canvas.copy(&texture, None, None)?;
+    canvas.copy(texture, None, None)?;
     canvas.present();
     Ok(())
 }
@@ -2,29 +2,27 @@ use super::cpu;
 use super::ppu;
 use super::apu;
 use super::cartridge;
+use super::nes_bus;
+use super::Console;
 
 use std::fs::{DirEntry, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
 #[derive(serde::Serialize, serde::Deserialize)]
-struct SaveState {
-    cpu: cpu::serialize::CpuData,
-    ppu: ppu::serialize::PpuData,
-    apu: apu::serialize::ApuData,
-    mapper: cartridge::serialize::MapperData,
+pub struct SaveState {
+    pub cpu: cpu::serialize::CpuData,
+    pub bus: nes_bus::BusData,
+    pub ppu: ppu::serialize::PpuData,
+    pub apu: apu::serialize::ApuData,
+    pub mapper: cartridge::serialize::MapperData,
 }
 
-pub fn save_state(cpu: &cpu::Cpu, save_file: &PathBuf) -> Result<(), String> {
-    let data = SaveState{
-        cpu: cpu.save_state(),
-        ppu: cpu.ppu.save_state(),
-        apu: cpu.apu.save_state(),
-        mapper: cpu.mapper.borrow().save_state(),
-    };
+pub fn save_state(console: &Console, save_file: &Path) -> Result<(), String> {
+    let data = console.snapshot();
     let serialized = serde_json::to_string(&data)
         .map_err(|e| e.to_string())?;
-    let mut f = File::create(&save_file)
+    let mut f = File::create(save_file)
         .expect("could not create output file for save state");
     f.write_all(serialized.as_bytes())
         .map_err(|_| "couldn't write serialized data to file".to_string())?;
@@ -32,9 +30,9 @@ pub fn save_state(cpu: &cpu::Cpu, save_file: &PathBuf) -> Result<(), String> {
     Ok(())
 }
 
-pub fn load_state(cpu: &mut cpu::Cpu, save_file: &PathBuf) -> Result<(), String> {
-    if Path::new(&save_file).exists() {
-        let mut f = File::open(save_file.clone())
+pub fn load_state(console: &mut Console, save_file: &Path) -> Result<(), String> {
+    if save_file.exists() {
+        let mut f = File::open(save_file)
             .map_err(|e| e.to_string())?;
         let mut serialized_data = vec![];
         f.read_to_end(&mut serialized_data)
@@ -43,10 +41,7 @@ pub fn load_state(cpu: &mut cpu::Cpu, save_file: &PathBuf) -> Result<(), String>
             .map_err(|e| e.to_string())?;
         let state: SaveState = serde_json::from_str(serialized_string)
             .map_err(|e| e.to_string())?;
-        cpu.load_state(state.cpu);
-        cpu.ppu.load_state(state.ppu);
-        cpu.apu.load_state(state.apu);
-        cpu.mapper.borrow_mut().load_state(state.mapper);
+        console.restore(state);
         println!("loading save state from file: {:?}", save_file);
         Ok(())
     } else {
@@ -54,13 +49,16 @@ pub fn load_state(cpu: &mut cpu::Cpu, save_file: &PathBuf) -> Result<(), String>
     }
 }
 
-pub fn find_next_filename(filepath: &PathBuf, new_ext: Option<&str>) -> Option<PathBuf> {
+pub fn find_next_filename(filepath: &Path, new_ext: Option<&str>) -> Option<PathBuf> {
     let path = match filepath.parent()?.to_str()? {
         "" => ".",
         x => x,
     };
     let stem = filepath.file_stem()?.to_str()?;
-    let ext = new_ext.or(Some(filepath.extension()?.to_str()?)).unwrap();
+    let ext = match new_ext {
+        Some(e) => e,
+        None => filepath.extension()?.to_str()?,
+    };
     let sep = std::path::MAIN_SEPARATOR.to_string();
     let mut i = 0;
     loop {
@@ -73,13 +71,16 @@ pub fn find_next_filename(filepath: &PathBuf, new_ext: Option<&str>) -> Option<P
     }
 }
 
-pub fn find_last_save_state(filepath: &PathBuf, new_ext: Option<&str>) -> Option<PathBuf> {
+pub fn find_last_save_state(filepath: &Path, new_ext: Option<&str>) -> Option<PathBuf> {
     let path = match filepath.parent()?.to_str()? {
         "" => Path::new("."),
         _ => filepath.parent()?,
     };
     let stem = filepath.file_stem()?.to_str()?;
-    let ext = new_ext.or(Some(filepath.extension()?.to_str()?)).unwrap();
+    let ext = match new_ext {
+        Some(e) => e,
+        None => filepath.extension()?.to_str()?,
+    };
     let files = std::fs::read_dir(path).expect("couldn't read directory");
     let mut save_states = files
         .filter(|dir_entry| {
@@ -0,0 +1,69 @@
+use super::Console;
+
+// Blargg's test ROMs (instr_test-v5, apu_test, ppu_vbl_nmi, etc.) report their results in cartridge RAM:
+//     $6000       status: $80 while the test is running, $81 if the console needs to be reset,
+//                 otherwise the final result code (0 is a pass)
+//     $6001-$6003 signature DE B0 61, so we know the other bytes mean something
+//     $6004-      zero-terminated text, the same message the ROM prints on screen
+const STATUS_ADDRESS: usize = 0x6000;
+const SIGNATURE_ADDRESS: usize = 0x6001;
+const TEXT_ADDRESS: usize = 0x6004;
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_NEEDS_RESET: u8 = 0x81;
+// "Reset must be delayed at least 100 msec after $81 is written," so wait this many frames.
+const RESET_DELAY_FRAMES: usize = 6;
+
+pub struct TestResult {
+    pub code: u8,        // 0 means the test passed, anything else identifies the failure
+    pub message: String, // text the ROM wrote, usually the test name and why it failed
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.code == 0
+    }
+}
+
+// Runs a test ROM until it reports a result, resetting the console when it asks.
+// Gives up with an error if there's no result after max_frames.
+pub fn run_test_rom(console: &mut Console, max_frames: usize) -> Result<TestResult, String> {
+    let mut reset_frame = None; // when to reset, if the ROM asked for it
+    for frame in 0..max_frames {
+        console.step_frame(0);
+        if !signature_present(console) {
+            continue;
+        }
+        match read_cartridge(console, STATUS_ADDRESS) {
+            STATUS_RUNNING => reset_frame = None,
+            STATUS_NEEDS_RESET => match reset_frame {
+                None => reset_frame = Some(frame + RESET_DELAY_FRAMES),
+                Some(f) if f == frame => console.reset(),
+                Some(_) => (),
+            },
+            code => return Ok(TestResult { code, message: read_message(console) }),
+        }
+    }
+    let message = if signature_present(console) { read_message(console) } else { String::new() };
+    Err(format!("no test result after {} frames. {}", max_frames, message.trim()))
+}
+
+fn signature_present(console: &Console) -> bool {
+    (0..3).all(|i| read_cartridge(console, SIGNATURE_ADDRESS + i) == SIGNATURE[i])
+}
+
+fn read_message(console: &Console) -> String {
+    let mut text = vec![];
+    for address in TEXT_ADDRESS..=0x7FFF {
+        match read_cartridge(console, address) {
+            0 => break,
+            byte => text.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&text).to_string()
+}
+
+// Look at cartridge memory directly instead of through the CPU, so we don't disturb anything.
+fn read_cartridge(console: &Console, address: usize) -> u8 {
+    console.cpu.bus.mapper.borrow().read(address).unwrap_or(0)
+}
@@ -0,0 +1,107 @@
+use super::Console;
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+// How many matching lines to show before a divergence
+const CONTEXT_LINES: usize = 5;
+
+pub struct TraceOptions {
+    pub output: Option<PathBuf>,     // file to write the trace to, instead of stdout
+    pub reference: Option<PathBuf>,  // log to compare the trace against, like nestest.log
+    pub start_pc: Option<usize>,     // start here instead of at the reset vector (nestest's automated mode starts at $C000)
+    pub max_instructions: usize,
+}
+
+// Runs the console one instruction at a time, writing a nestest.log-style line for each.
+// With a reference log, stops at the first line that doesn't match it and returns an error describing the difference.
+pub fn run_trace(console: &mut Console, options: &TraceOptions) -> Result<(), String> {
+    let mut output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(File::create(path).map_err(|e| format!("could not create {:?}: {}", path, e))?),
+        None => Box::new(std::io::stdout()),
+    };
+    let mut reference = match &options.reference {
+        Some(path) => {
+            let f = File::open(path).map_err(|e| format!("could not open {:?}: {}", path, e))?;
+            Some(BufReader::new(f).lines())
+        },
+        None => None,
+    };
+    if let Some(pc) = options.start_pc {
+        console.cpu.set_pc(pc);
+    }
+    console.cpu.tracing = true;
+    let mut recent_lines = VecDeque::new();
+    let mut count = 0;
+    while count < options.max_instructions {
+        console.cpu.trace_line.clear();
+        console.step();
+        if console.cpu.trace_line.is_empty() {
+            // the only steps that don't run an instruction are a jammed CPU's
+            return Err(format!("CPU jammed after {} instructions", count));
+        }
+        let expected = match reference.as_mut().map(|lines| lines.next()) {
+            Some(Some(l)) => Some(l.map_err(|e| e.to_string())?),
+            Some(None) => break, // reached the end of the reference
+            None => None,
+        };
+        count += 1;
+        let line = console.cpu.trace_line.clone();
+        writeln!(output, "{}", line).map_err(|e| e.to_string())?;
+        if let Some(expected) = expected {
+            let differences = compare_lines(&expected, &line);
+            if !differences.is_empty() {
+                let mut report = format!("trace diverged from reference at line {} ({}):\n", count, differences.join(", "));
+                for l in recent_lines.iter() {
+                    report += &format!("  {}\n", l);
+                }
+                report += &format!("- {}\n+ {}", expected, line);
+                return Err(report);
+            }
+            recent_lines.push_back(line);
+            if recent_lines.len() > CONTEXT_LINES {
+                recent_lines.pop_front();
+            }
+        }
+    }
+    if reference.is_some() {
+        println!("trace matched reference for {} instructions", count);
+    }
+    Ok(())
+}
+
+// Returns the names of the fields that differ between two trace lines. The disassembly isn't compared,
+// since the values it shows for memory depend on the emulator. PPU position and cycle count are only
+// compared if both lines have them.
+pub fn compare_lines(expected: &str, actual: &str) -> Vec<&'static str> {
+    let mut differences = vec![];
+    if expected.get(0..4) != actual.get(0..4) {
+        differences.push("PC");
+    }
+    if expected.get(6..14).map(str::trim) != actual.get(6..14).map(str::trim) {
+        differences.push("opcode bytes");
+    }
+    for (name, key) in [("A", " A:"), ("X", " X:"), ("Y", " Y:"), ("P", " P:"), ("SP", " SP:"), ("PPU", " PPU:"), ("CYC", " CYC:")].iter() {
+        let e = field(expected, key);
+        let a = field(actual, key);
+        let optional = *name == "PPU" || *name == "CYC";
+        if e != a && !(optional && (e.is_none() || a.is_none())) {
+            differences.push(name);
+        }
+    }
+    differences
+}
+
+// The value after a key like " A:", up to the next key
+fn field(line: &str, key: &str) -> Option<String> {
+    let start = line.find(key)? + key.len();
+    let rest = &line[start..];
+    let value = match key {
+        // "PPU:  0, 21" has spaces in it, so take everything up to CYC
+        " PPU:" => rest.split(" CYC").next()?,
+        _ => rest.split_whitespace().next()?,
+    };
+    Some(value.replace(' ', ""))
+}
@@ -0,0 +1,245 @@
+// The windowed frontend: an SDL window, audio device and keyboard, with hotkeys for save states and resets.
+// Only built with the sdl feature; everything else the binary does is headless.
+
+use nestur::Console;
+use nestur::power_on::PowerOnOptions;
+use nestur::cartridge::archive::rom_entries;
+use nestur::cartridge::patch::PatchChoice;
+use nestur::state::{save_state, load_state, find_next_filename, find_last_save_state};
+use super::input::poll_buttons;
+use super::screen::{init_window, draw_to_window};
+use super::audio;
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, Duration};
+
+use sdl2::Sdl;
+use sdl2::render::{Canvas, Texture};
+use sdl2::keyboard::Keycode;
+use sdl2::EventPump;
+use sdl2::event::Event;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::video::Window;
+use sdl2::messagebox::*;
+
+// use cpuprofiler::PROFILER;
+
+enum GameExitMode {
+    QuitApplication,
+    NewGame(Box<Console>), // already loaded, so a bad ROM never stops the current game
+    Reset,
+    PowerCycle,
+    Nothing,
+}
+
+// Opens the window and plays rom if there is one, or waits for one to be dropped on the window.
+pub fn play(rom: Option<String>, patch: PatchChoice, power_on: PowerOnOptions) -> Result<(), String> {
+    // Set up screen
+    let sdl_context = sdl2::init()?;
+    let mut event_pump = sdl_context.event_pump()?;
+    let (mut canvas, texture_creator) = init_window(&sdl_context).expect("Could not create window");
+    let mut texture = texture_creator.create_texture_streaming(
+        PixelFormatEnum::RGB24, 256, 240)
+        .map_err(|e| e.to_string())?;
+
+    let first_game = rom.and_then(|f| load_game(&f, &patch, power_on, canvas.window()));
+    let mut console = match first_game {
+        Some(console) => console,
+        None => {
+            show_simple_message_box(
+                MessageBoxFlag::INFORMATION, "Welcome to Nestur!", INSTRUCTIONS, canvas.window()
+            ).map_err(|e| e.to_string())?;
+            'waiting: loop {
+                for event in event_pump.poll_iter() {
+                    match event {
+                        Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. }
+                            => return Ok(()),
+                        Event::DropFile{ filename: f, .. } => {
+                            if let Some(console) = load_game(&f, &PatchChoice::Found, power_on, canvas.window()) {
+                                break 'waiting console;
+                            }
+                        },
+                        _ => (),
+                    }
+                }
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        },
+    };
+    loop {
+        let res = run_game(&sdl_context, &mut event_pump, &mut canvas, &mut texture, console, power_on);
+        match res {
+            Ok(Some(GameExitMode::NewGame(next_console))) => console = *next_console,
+            Ok(None) | Ok(Some(GameExitMode::QuitApplication)) => return Ok(()),
+            Err(e) => return Err(e),
+            Ok(Some(GameExitMode::Reset)) | Ok(Some(GameExitMode::PowerCycle)) | Ok(Some(GameExitMode::Nothing))
+                => panic!("run_game() handles reset and power cycle itself, and shouldn't return exit mode Nothing"),
+        }
+    }
+}
+
+// Loads a ROM to play in the window, asking which game to play if it's a .zip with several.
+// If it can't be played, says why in a message box and returns None.
+fn load_game(filename: &str, patch: &PatchChoice, power_on: PowerOnOptions, window: &Window) -> Option<Console> {
+    let console = rom_entries(filename).and_then(|entries| {
+        let entry = if entries.len() > 1 { choose_entry(filename, &entries, window) } else { None };
+        Console::open(filename, entry.as_deref(), patch, power_on)
+    });
+    match console {
+        Ok(console) => Some(console),
+        Err(e) => {
+            println!("could not load {}: {}", filename, e);
+            let message = format!("{}\n\n{}", filename, e);
+            if let Err(e) = show_simple_message_box(MessageBoxFlag::ERROR, "Could not load game", &message, window) {
+                println!("{}", e);
+            }
+            None
+        },
+    }
+}
+
+// Asks which game to play from an archive. Only the first few fit in the message box.
+// None if the box was closed, which means the first one.
+fn choose_entry(filename: &str, entries: &[String], window: &Window) -> Option<String> {
+    let buttons = entries.iter().take(8).enumerate()
+        .map(|(i, entry)| ButtonData { flags: MessageBoxButtonFlag::NOTHING, button_id: i as i32, text: entry })
+        .collect::<Vec<_>>();
+    let message = format!("{} has more than one game in it. Which one should be played?", filename);
+    match show_message_box(MessageBoxFlag::INFORMATION, &buttons, "Choose a game", &message, window, None) {
+        Ok(ClickedButton::CustomButton(button)) => Some(entries[button.button_id as usize].clone()),
+        _ => None,
+    }
+}
+
+fn run_game(
+        sdl_context: &Sdl,
+        event_pump: &mut EventPump,
+        canvas: &mut Canvas<Window>,
+        texture: &mut Texture,
+        mut console: Console,
+        power_on: PowerOnOptions,
+    ) -> Result<Option<GameExitMode>, String> {
+
+    let filename = console.filename().to_string();
+    println!("loading game {}", filename);
+
+    // Set up audio
+    let apu_buffer = Arc::new(Mutex::new(Vec::<f32>::new())); // stays in this thread, receives raw samples between frames
+    let sdl_buffer = Arc::clone(&apu_buffer); // used in audio device's callback to select the samples it needs
+    let audio_device = audio::initialize(sdl_context, sdl_buffer).expect("Could not create audio device");
+    let mut audio_started = false;
+
+    // Initialize hardware components
+    let filepath = Path::new(&filename).to_path_buf();
+    let mut button_states = 0;
+
+    // For throttling to 60 FPS
+    let mut timer = Instant::now();
+    let mut fps_timer = Instant::now();
+    let mut fps = 0;
+
+    // PROFILER.lock().unwrap().start("./main.profile").unwrap();
+    'running: loop {
+        // run the console until the PPU has a full frame for us
+        let frame = console.step_frame(button_states);
+        fps += 1; // keep track of how many frames we've rendered this second
+        draw_to_window(texture, canvas, &frame.pixels)?; // draw the buffer to the window with SDL
+        let mut b = apu_buffer.lock().unwrap(); // unlock mutex to the real buffer
+        b.append(&mut console.audio_samples()); // send this frame's audio data
+        drop(b);
+        if !audio_started {
+            audio_started = true;
+            audio_device.resume();
+        }
+        let now = Instant::now();
+        // if we're running faster than 60Hz, kill time
+        if now < timer + Duration::from_millis(1000/60) {
+            std::thread::sleep(timer + Duration::from_millis(1000/60) - now);
+        }
+        timer = Instant::now();
+        let outcome = process_events(event_pump, &filepath, &mut console, power_on, canvas.window());
+        match outcome {
+            GameExitMode::QuitApplication => break 'running,
+            GameExitMode::Reset => console.reset(),
+            GameExitMode::PowerCycle => console.power_cycle(),
+            GameExitMode::NewGame(c) => return Ok(Some(GameExitMode::NewGame(c))),
+            GameExitMode::Nothing => (),
+        }
+        // handle keyboard events
+        button_states = poll_buttons(event_pump);
+        // calculate fps
+        let now = Instant::now();
+        if now > fps_timer + Duration::from_secs(1) {
+            println!("frames per second: {}", fps);
+            fps = 0;
+            fps_timer = now;
+        }
+    }
+    // PROFILER.lock().unwrap().stop().unwrap();
+    console.save_battery_backed_ram();
+    Ok(None)
+}
+
+fn process_events(event_pump: &mut EventPump, filepath: &Path, console: &mut Console, power_on: PowerOnOptions,
+        window: &Window) -> GameExitMode {
+    for event in event_pump.poll_iter() {
+        match event {
+            Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. }
+                => return GameExitMode::QuitApplication,
+            Event::KeyDown{ keycode: Some(Keycode::F2), .. }
+                => return GameExitMode::Reset,
+            Event::KeyDown{ keycode: Some(Keycode::F3), .. }
+                => return GameExitMode::PowerCycle,
+            Event::KeyDown{ keycode: Some(Keycode::F5), .. } => {
+                let save_file = find_next_filename(filepath, Some("dat"))
+                    .expect("could not generate save state filename");
+                let res: Result<(), String> = save_state(console, &save_file)
+                    .or_else(|e| {println!("{}", e); Ok(())});
+                res.unwrap();
+            },
+            Event::KeyDown{ keycode: Some(Keycode::F9), .. } => {
+                match find_last_save_state(filepath, Some("dat")) {
+                    Some(p) => {
+                        let res: Result<(), String> = load_state(console, &p)
+                            .or_else(|e| { println!("{}", e); Ok(()) } );
+                        res.unwrap();
+                    },
+                    None => println!("no save state found for {:?}", filepath)
+                }
+            },
+            Event::DropFile{ timestamp: _t, window_id: _w, filename: f } => {
+                if f.len() > 4 && &f[f.len()-4..] == ".dat" {
+                    let p = Path::new(&f).to_path_buf();
+                    let res: Result<(), String> = load_state(console, &p)
+                        .or_else(|e| {println!("{}", e); Ok(())});
+                    res.unwrap();
+                // } else if f.len() > 4 && &f[f.len()-4..] == ".nes" {
+                } else {
+                    // save first, in case the new file is the same game and is about to read the save back in
+                    console.save_battery_backed_ram();
+                    if let Some(new_console) = load_game(&f, &PatchChoice::Found, power_on, window) {
+                        return GameExitMode::NewGame(Box::new(new_console));
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+    GameExitMode::Nothing
+}
+
+const INSTRUCTIONS: &str = "To play a game, drag an INES file (extension .nes), or a .zip with one in it, onto the main window.
+To save the game state, press F5. To load the most recent save state, press F9.
+To load another save state file, drag a .dat file onto the window while the game is running.
+Battery-backed RAM saves (what the NES cartridges have) will be written to a .sav file if used.
+To press the console's reset button, press F2. To turn it off and on again, press F3.
+
+Controls
+------------
+A: D
+B: F
+Start: enter
+Select: (right) shift
+Up/Down/Left/Right: arrow keys
+";
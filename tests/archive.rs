@@ -0,0 +1,48 @@
+// Checks which files in a .zip are offered as games, and that reading one is bounded.
+
+use nestur::cartridge::archive::{read_rom_file, rom_entries, MAX_ROM_SIZE};
+
+use std::io::Write;
+use std::path::PathBuf;
+
+fn write_zip(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("nestur-{}-{}.zip", name, std::process::id()));
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+    let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (file_name, data) in files {
+        zip.start_file(*file_name, options).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    zip.finish().unwrap();
+    path
+}
+
+#[test]
+fn only_ines_files_are_offered() {
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.resize(0x10 + 0x4000, 0xEA);
+    let path = write_zip("entries", &[
+        ("readme.txt", b"not a game"),
+        ("music.nsf", b"NESM\x1A\x01"),
+        ("disk.fds", b"FDS\x1A\x01"),
+        ("renamed.bin", &rom),
+        ("games/game.nes", &rom),
+    ]);
+    let name = path.to_str().unwrap();
+    assert_eq!(rom_entries(name).unwrap(), vec!["renamed.bin".to_string(), "games/game.nes".to_string()]);
+    let (data, filename) = read_rom_file(name, Some("games/game.nes")).unwrap();
+    assert_eq!(data, rom);
+    assert_eq!(PathBuf::from(filename), path.with_file_name("game.nes"));
+    assert_eq!(read_rom_file(name, None).unwrap().0, rom);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn oversized_entries_are_rejected() {
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A];
+    rom.resize(MAX_ROM_SIZE + 1, 0);
+    let path = write_zip("oversized", &[("huge.nes", &rom)]);
+    let name = path.to_str().unwrap();
+    assert!(read_rom_file(name, None).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,80 @@
+// Checks the header database: that the bundled one finds games by the hash of their PRG and CHR data,
+// and that a match overrides what the ROM's header says when the cartridge is loaded.
+
+use nestur::cartridge::{Cartridge, Mirror};
+use nestur::cartridge::game_db::{lookup, GameDatabase};
+
+#[test]
+fn bundled_database_finds_super_mario_bros() {
+    let game = lookup(0x3337EC46, "EA343F4E445A9050D4B4FBAC2C77D0693B1D0922").expect("Super Mario Bros. should be in nes20db.xml");
+    assert_eq!(game.name, "Super Mario Bros. (World).nes");
+    assert_eq!(game.mapper, 0);
+    assert_eq!(game.mirroring, Some('V'));
+    assert!(!game.battery);
+    // a CRC32 collision with a different SHA-1 isn't the same game
+    assert_eq!(lookup(0x3337EC46, "0000000000000000000000000000000000000000"), None);
+}
+
+#[test]
+fn database_fixes_bad_header() {
+    // NROM with one bank each of PRG and CHR, whose header says horizontal mirroring and no battery
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend((0..0x4000 + 0x2000).map(|i| (i * 7) as u8));
+    let data = &rom[0x10..];
+    let crc32 = crc32fast::hash(data);
+    let sha1 = sha1_smol::Sha1::from(data).digest().to_string().to_uppercase();
+    let database = format!("<nes20db>
+        <game>
+            <!-- Test Game.nes -->
+            <rom size=\"24576\" crc32=\"{:08X}\" sha1=\"{}\"/>
+            <pcb mapper=\"0\" submapper=\"0\" mirroring=\"V\" battery=\"1\"/>
+            <prgnvram size=\"8192\"/>
+            <console type=\"0\" region=\"0\"/>
+        </game>
+    </nes20db>", crc32, sha1);
+    let database = GameDatabase::parse(&database);
+    assert!(database.lookup(crc32, &sha1).is_some());
+    assert_eq!(database.lookup(crc32 ^ 1, &sha1), None);
+
+    let unfixed = Cartridge::from_bytes(rom.clone(), String::new()).unwrap().info();
+    assert_eq!(unfixed.mirroring, Mirror::Horizontal);
+    assert!(unfixed.database_fixes.is_empty());
+
+    let info = Cartridge::from_bytes_with_database(rom.clone(), String::new(), &database).unwrap().info();
+    assert_eq!(info.mirroring, Mirror::Vertical);
+    assert!(info.battery);
+    assert_eq!(info.prg_ram, 0);
+    assert_eq!(info.prg_nvram, 8192);
+    // an iNES header can't say there's no plain PRG-RAM, so only what it could have gotten right is reported
+    assert_eq!(info.database_fixes, vec![
+        "mirroring: Horizontal -> Vertical".to_string(),
+        "battery: false -> true".to_string(),
+    ]);
+
+    // but an NES 2.0 header can, so with one that says 8KB of PRG-RAM and no battery the RAM sizes are wrong too
+    rom[7] = 0x08;
+    rom[10] = 0x07;
+    let info = Cartridge::from_bytes_with_database(rom, String::new(), &database).unwrap().info();
+    assert_eq!(info.database_fixes, vec![
+        "mirroring: Horizontal -> Vertical".to_string(),
+        "battery: false -> true".to_string(),
+        "PRG-RAM: 8192 -> 0".to_string(),
+        "PRG-NVRAM: 0 -> 8192".to_string(),
+    ]);
+}
+
+#[test]
+fn correct_ines_header_needs_no_fixes() {
+    // vertical mirroring, no battery, and the database has no PRG-RAM entry for it
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    rom.extend((0..0x4000 + 0x2000).map(|i| (i * 3) as u8));
+    let crc32 = crc32fast::hash(&rom[0x10..]);
+    let database = GameDatabase::parse(&format!("<nes20db><game>
+        <rom crc32=\"{:08X}\"/>
+        <pcb mapper=\"0\" submapper=\"0\" mirroring=\"V\" battery=\"0\"/>
+        <console type=\"0\" region=\"0\"/>
+    </game></nes20db>", crc32));
+    let info = Cartridge::from_bytes_with_database(rom, String::new(), &database).unwrap().info();
+    assert!(info.database_fixes.is_empty(), "{:?}", info.database_fixes);
+    assert_eq!(info.prg_ram, 0);
+}
@@ -0,0 +1,82 @@
+// Checks the simplest boards, NROM, UxROM and CNROM, through the Mapper interface: PRG-RAM at $6000-$7FFF
+// sized from the header, and a trainer copied into it at $7000. Also loading and saving battery-backed RAM.
+
+use nestur::cartridge::{Cartridge, get_mapper};
+
+// A ROM with one 16KB PRG bank and one 8KB CHR bank, and an NES 2.0 header giving its mapper and PRG-RAM size.
+fn rom(mapper: u8, prg_ram_shift: u8, trainer: Option<&[u8]>) -> Vec<u8> {
+    let flags6 = (mapper << 4) | if trainer.is_some() { 1 << 2 } else { 0 };
+    let mut rom = vec![0x4E, 0x45, 0x53, 0x1A, 1, 1, flags6, 0x08, 0, 0, prg_ram_shift, 0, 0, 0, 0, 0];
+    if let Some(trainer) = trainer {
+        rom.extend_from_slice(trainer);
+    }
+    rom.extend((0..0x4000 + 0x2000).map(|i| (i * 7) as u8));
+    rom
+}
+
+#[test]
+fn prg_ram_round_trips() {
+    for mapper in [0, 2, 3].iter() {
+        // a shift of 7 is 64 << 7, 8KB
+        let cart = Cartridge::from_bytes(rom(*mapper, 7, None), String::new()).unwrap();
+        let mapper = get_mapper(cart).unwrap();
+        let mut mapper = mapper.borrow_mut();
+        assert_eq!(mapper.read(0x6000), Some(0));
+        mapper.write(0x6000, 0x5A);
+        mapper.write(0x7FFF, 0xA5);
+        assert_eq!(mapper.read(0x6000), Some(0x5A));
+        assert_eq!(mapper.read(0x7FFF), Some(0xA5));
+    }
+}
+
+#[test]
+fn no_prg_ram_is_open_bus() {
+    for mapper in [0, 2, 3].iter() {
+        let cart = Cartridge::from_bytes(rom(*mapper, 0, None), String::new()).unwrap();
+        let mapper = get_mapper(cart).unwrap();
+        let mut mapper = mapper.borrow_mut();
+        mapper.write(0x6000, 0x5A);
+        assert_eq!(mapper.read(0x6000), None);
+    }
+}
+
+#[test]
+fn trainer_is_copied_to_7000() {
+    let trainer: Vec<u8> = (0..0x200).map(|i| (i as u8) ^ 0xFF).collect();
+    for mapper in [0, 2, 3].iter() {
+        let cart = Cartridge::from_bytes(rom(*mapper, 7, Some(&trainer)), String::new()).unwrap();
+        let mapper = get_mapper(cart).unwrap();
+        let mapper = mapper.borrow();
+        assert_eq!(mapper.read(0x6FFF), Some(0));
+        assert_eq!(mapper.read(0x7000), Some(0xFF));
+        assert_eq!(mapper.read(0x71FF), Some(0x00));
+        assert_eq!(mapper.read(0x7200), Some(0));
+    }
+}
+
+#[test]
+fn battery_saves_of_the_wrong_size_are_ignored() {
+    let dir = std::env::temp_dir().join(format!("nestur-battery-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let rom_file = dir.join("game.nes");
+    let save_file = dir.join("game.sav");
+    let mut data = rom(0, 0, None);
+    data[6] |= 1 << 1; // battery
+    data[10] = 0x70; // 8KB of PRG-NVRAM
+    let cart = Cartridge::from_bytes(data, rom_file.to_string_lossy().to_string()).unwrap();
+    assert_eq!(cart.save_file(), Some(save_file.clone()));
+
+    let mut ram = vec![0x11; 0x2000];
+    cart.load_battery_backed_ram(&mut ram); // no save yet
+    assert_eq!(ram, vec![0x11; 0x2000]);
+    std::fs::write(&save_file, vec![0x22; 0x8000]).unwrap();
+    cart.load_battery_backed_ram(&mut ram);
+    assert_eq!(ram, vec![0x11; 0x2000]);
+
+    ram[0] = 0x33;
+    cart.save_battery_backed_ram(&ram);
+    let mut loaded = vec![0; 0x2000];
+    cart.load_battery_backed_ram(&mut loaded);
+    assert_eq!(loaded, ram);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
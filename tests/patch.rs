@@ -0,0 +1,156 @@
+// Applies small IPS, UPS and BPS patches built in memory, and checks that bad ones are turned away.
+
+use nestur::cartridge::RomError;
+use nestur::cartridge::patch::{apply_patch, load_patched, PatchChoice};
+
+fn rom() -> Vec<u8> {
+    (0..16).map(|i| 0x10 + i as u8).collect()
+}
+
+// UPS/BPS variable-length number
+fn number(mut n: usize, out: &mut Vec<u8>) {
+    loop {
+        let x = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            out.push(0x80 | x);
+            return;
+        }
+        out.push(x);
+        n -= 1;
+    }
+}
+
+// Adds the CRC32s of the original file, the patched file, and the patch so far
+fn finish(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+    patch.extend_from_slice(&crc32fast::hash(source).to_le_bytes());
+    patch.extend_from_slice(&crc32fast::hash(target).to_le_bytes());
+    let crc = crc32fast::hash(&patch);
+    patch.extend_from_slice(&crc.to_le_bytes());
+    patch
+}
+
+fn is_patch_error(result: Result<Vec<u8>, RomError>) -> bool {
+    matches!(result, Err(RomError::Patch(_)))
+}
+
+#[test]
+fn ips_writes_records_and_runs() {
+    let mut patch = b"PATCH".to_vec();
+    patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]); // 2 bytes at 2
+    patch.extend_from_slice(&[0x00, 0x00, 0x0E, 0x00, 0x00, 0x00, 0x04, 0xCC]); // RLE: 4 x $CC at 14, past the end
+    patch.extend_from_slice(b"EOF");
+    let mut expected = rom();
+    expected[2..4].copy_from_slice(&[0xAA, 0xBB]);
+    expected.truncate(14);
+    expected.extend_from_slice(&[0xCC; 4]);
+    assert_eq!(apply_patch(&rom(), &patch).unwrap(), expected);
+
+    // with the truncation extension after EOF
+    patch.extend_from_slice(&[0x00, 0x00, 0x08]);
+    assert_eq!(apply_patch(&rom(), &patch).unwrap(), expected[..8].to_vec());
+
+    // a record cut short
+    assert!(is_patch_error(apply_patch(&rom(), b"PATCH\x00\x00\x02\x00\x04\xAA")));
+    assert!(is_patch_error(apply_patch(&rom(), b"not a patch")));
+}
+
+#[test]
+fn ups_xors_in_changes() {
+    let source = rom();
+    let mut target = source.clone();
+    target[4] = 0xAA;
+    target[5] = 0xBB;
+    target.extend_from_slice(&[0xCC, 0xDD]);
+    let mut patch = b"UPS1".to_vec();
+    number(source.len(), &mut patch);
+    number(target.len(), &mut patch);
+    number(4, &mut patch); // skip to 4
+    patch.extend_from_slice(&[source[4] ^ 0xAA, source[5] ^ 0xBB, 0]);
+    number(16 - 7, &mut patch); // from 7, past the terminator, to 16
+    patch.extend_from_slice(&[0xCC, 0xDD, 0]);
+    let patch = finish(patch, &source, &target);
+    assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+
+    // for a different ROM
+    let mut other = source.clone();
+    other[0] ^= 1;
+    assert!(is_patch_error(apply_patch(&other, &patch)));
+    // damaged in transit
+    let mut corrupt = patch.clone();
+    corrupt[8] ^= 1;
+    assert!(is_patch_error(apply_patch(&source, &corrupt)));
+    // a result that doesn't match its CRC
+    let mut wrong_target = target.clone();
+    wrong_target[0] ^= 1;
+    let wrong = finish(patch[..patch.len() - 12].to_vec(), &source, &wrong_target);
+    assert!(is_patch_error(apply_patch(&source, &wrong)));
+}
+
+#[test]
+fn bps_builds_from_all_four_commands() {
+    let source = rom();
+    let mut target = source[..4].to_vec();
+    target.extend_from_slice(&[0xAA, 0xBB]);
+    target.extend_from_slice(&source[6..]);
+    target.extend_from_slice(&source[..2]);
+    let mut patch = b"BPS1".to_vec();
+    number(source.len(), &mut patch);
+    number(target.len(), &mut patch);
+    number(0, &mut patch); // no metadata
+    number((3 << 2) | 0, &mut patch); // SourceRead 4
+    number((1 << 2) | 1, &mut patch); // TargetRead 2
+    patch.extend_from_slice(&[0xAA, 0xBB]);
+    number((9 << 2) | 2, &mut patch); // SourceCopy 10, from 6
+    number(6 << 1, &mut patch);
+    number((1 << 2) | 3, &mut patch); // TargetCopy 2, from 0
+    number(0, &mut patch);
+    let patch = finish(patch, &source, &target);
+    assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+
+    let mut other = source.clone();
+    other[15] ^= 1;
+    assert!(is_patch_error(apply_patch(&other, &patch)));
+    let mut corrupt = patch.clone();
+    corrupt[10] ^= 1;
+    assert!(is_patch_error(apply_patch(&source, &corrupt)));
+}
+
+#[test]
+fn huge_targets_are_rejected_before_allocating() {
+    let source = rom();
+    for magic in [&b"UPS1"[..], &b"BPS1"[..]].iter() {
+        let mut patch = magic.to_vec();
+        number(source.len(), &mut patch);
+        number(1 << 40, &mut patch);
+        if *magic == b"BPS1" {
+            number(0, &mut patch);
+        }
+        let patch = finish(patch, &source, &[]);
+        match apply_patch(&source, &patch) {
+            Err(RomError::Patch(reason)) => assert!(reason.contains("MiB"), "{}", reason),
+            _ => panic!("a 1 TiB patch target should be rejected"),
+        }
+    }
+}
+
+#[test]
+fn patch_next_to_rom_gets_its_own_save_name() {
+    let dir = std::env::temp_dir().join(format!("nestur-patch-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let rom_file = dir.join("game.nes");
+    let patch_file = dir.join("game.ips");
+    std::fs::write(&rom_file, rom()).unwrap();
+    std::fs::write(&patch_file, b"PATCH\x00\x00\x00\x00\x01\xFFEOF").unwrap();
+    let rom_name = rom_file.to_str().unwrap();
+
+    let (data, filename) = load_patched(rom_name, None, &PatchChoice::Found).unwrap();
+    assert_eq!(data[0], 0xFF);
+    assert_eq!(filename, format!("{}.nes", patch_file.to_str().unwrap()));
+    assert_ne!(std::path::Path::new(&filename).with_extension("sav"), rom_file.with_extension("sav"));
+
+    let (data, filename) = load_patched(rom_name, None, &PatchChoice::Skip).unwrap();
+    assert_eq!(data, rom());
+    assert_eq!(filename, rom_name);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,181 @@
+// Runs the CPU against Tom Harte's ProcessorTests for the NES's 6502 (https://github.com/SingleStepTests/ProcessorTests,
+// nes6502/v1). Each case gives the registers and RAM before one instruction, what they should be after it, and every
+// bus cycle in between, so it checks cycle counts and dummy reads and writes along with the results.
+// The files are too big to check in, so the test is ignored unless asked for. Point PROCESSOR_TESTS_DIR at a directory
+// of them (00.json through ff.json):
+//     PROCESSOR_TESTS_DIR=path/to/nes6502/v1 cargo test --no-default-features --test processor_tests -- --ignored --nocapture
+// and PROCESSOR_TESTS_OPCODES=a9,9d runs just those opcodes.
+// A handful of hand-written cases in the same format, one or more for each addressing mode, are checked in under
+// tests/data and always run. Most of them sit at the edges of memory, where the program counter has to wrap.
+
+use nestur::cpu::Cpu;
+use nestur::cpu::bus::{Bus, FlatBus};
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Deserialize)]
+struct TestCase {
+    name: String,
+    initial: CpuState,
+    #[serde(rename = "final")]
+    expected: CpuState,
+    cycles: Vec<(usize, u8, String)>, // address, value, "read" or "write"
+}
+
+#[derive(Deserialize)]
+struct CpuState {
+    pc: usize,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(usize, u8)>,
+}
+
+// Flat RAM that remembers each cycle's access.
+struct RecordingBus {
+    ram: FlatBus,
+    cycles: Vec<(usize, u8, String)>,
+}
+
+impl Bus for RecordingBus {
+    fn read(&mut self, address: usize) -> u8 {
+        let value = self.ram.read(address);
+        self.cycles.push((address, value, "read".to_string()));
+        value
+    }
+
+    fn write(&mut self, address: usize, value: u8) {
+        self.ram.write(address, value);
+        self.cycles.push((address, value, "write".to_string()));
+    }
+}
+
+// What went wrong with one opcode's cases
+struct OpcodeReport {
+    cases: usize,
+    failures: usize,
+    first_failure: Option<String>,
+    cycle_counts: BTreeMap<(usize, usize), usize>, // (expected, actual) -> how many cases, for cases where they differ
+}
+
+#[test]
+fn addressing_mode_cases() {
+    let data = include_str!("data/addressing_modes.json");
+    let cases: Vec<TestCase> = serde_json::from_str(data).expect("could not parse addressing_modes.json");
+    let failures: Vec<String> = cases.iter()
+        .filter_map(|case| {
+            let (differences, _) = run_case(case);
+            match differences.is_empty() {
+                true => None,
+                false => Some(format!("\"{}\": {}", case.name, differences.join(", "))),
+            }
+        })
+        .collect();
+    assert!(failures.is_empty(), "{} of {} cases failed:\n{}", failures.len(), cases.len(), failures.join("\n"));
+}
+
+#[test]
+#[ignore = "needs the ProcessorTests files in PROCESSOR_TESTS_DIR"]
+fn processor_tests() {
+    let dir = std::env::var("PROCESSOR_TESTS_DIR")
+        .expect("PROCESSOR_TESTS_DIR should be a directory of ProcessorTests files (nes6502/v1)");
+    let opcodes: Vec<usize> = match std::env::var("PROCESSOR_TESTS_OPCODES") {
+        Ok(list) => list.split(',')
+            .map(|o| usize::from_str_radix(o.trim(), 16).expect("PROCESSOR_TESTS_OPCODES should be hex opcodes"))
+            .collect(),
+        Err(_) => (0..0x100).collect(),
+    };
+
+    let mut reports = BTreeMap::new();
+    for opcode in opcodes {
+        let path = Path::new(&dir).join(format!("{:02x}.json", opcode));
+        if !path.exists() {
+            continue;
+        }
+        let data = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("could not read {:?}: {}", path, e));
+        let cases: Vec<TestCase> = serde_json::from_str(&data).unwrap_or_else(|e| panic!("could not parse {:?}: {}", path, e));
+        let mut report = OpcodeReport { cases: cases.len(), failures: 0, first_failure: None, cycle_counts: BTreeMap::new() };
+        for case in cases.iter() {
+            let (differences, actual_cycles) = run_case(case);
+            if actual_cycles != case.cycles.len() {
+                *report.cycle_counts.entry((case.cycles.len(), actual_cycles)).or_insert(0) += 1;
+            }
+            if !differences.is_empty() {
+                report.failures += 1;
+                if report.first_failure.is_none() {
+                    report.first_failure = Some(format!("\"{}\": {}", case.name, differences.join(", ")));
+                }
+            }
+        }
+        reports.insert(opcode, report);
+    }
+    if reports.is_empty() {
+        panic!("no test files found in {}", dir);
+    }
+
+    let mut failed_opcodes = 0;
+    let mut summary = String::new();
+    for (opcode, report) in reports.iter() {
+        if report.failures == 0 {
+            continue;
+        }
+        failed_opcodes += 1;
+        summary += &format!("{:02x}: {} of {} cases failed, first {}\n", opcode, report.failures, report.cases,
+            report.first_failure.as_ref().unwrap());
+        for ((expected, actual), count) in report.cycle_counts.iter() {
+            summary += &format!("    {} cycles instead of {} in {} cases\n", actual, expected, count);
+        }
+    }
+    println!("{} of {} opcodes passed", reports.len() - failed_opcodes, reports.len());
+    if failed_opcodes > 0 {
+        panic!("{} opcodes failed:\n{}", failed_opcodes, summary);
+    }
+}
+
+// Runs one instruction and returns what didn't match, and how many cycles it took.
+fn run_case(case: &TestCase) -> (Vec<String>, usize) {
+    let bus = RecordingBus { ram: FlatBus::new(), cycles: vec![] };
+    let mut cpu = Cpu::new(bus);
+    for &(address, value) in case.initial.ram.iter() {
+        cpu.bus.ram.write(address, value);
+    }
+    cpu.pc = case.initial.pc;
+    cpu.s = case.initial.s;
+    cpu.a = case.initial.a;
+    cpu.x = case.initial.x;
+    cpu.y = case.initial.y;
+    cpu.p = case.initial.p;
+    cpu.bus.cycles.clear(); // forget the reset sequence
+
+    cpu.step();
+
+    let expected = &case.expected;
+    let mut differences = vec![];
+    for (name, actual, wanted) in [("PC", cpu.pc, expected.pc), ("S", cpu.s as usize, expected.s as usize),
+        ("A", cpu.a as usize, expected.a as usize), ("X", cpu.x as usize, expected.x as usize),
+        ("Y", cpu.y as usize, expected.y as usize), ("P", cpu.p as usize, expected.p as usize)].iter() {
+        if actual != wanted {
+            differences.push(format!("{} is {:02X}, expected {:02X}", name, actual, wanted));
+        }
+    }
+    for &(address, value) in expected.ram.iter() {
+        let actual = cpu.bus.ram.read(address);
+        if actual != value {
+            differences.push(format!("${:04X} is {:02X}, expected {:02X}", address, actual, value));
+        }
+    }
+    let actual_cycles = cpu.bus.cycles.len();
+    if actual_cycles != case.cycles.len() {
+        differences.push(format!("took {} cycles, expected {}", actual_cycles, case.cycles.len()));
+    } else if let Some(i) = (0..actual_cycles).find(|&i| cpu.bus.cycles[i] != case.cycles[i]) {
+        let (address, value, kind) = &cpu.bus.cycles[i];
+        let (e_address, e_value, e_kind) = &case.cycles[i];
+        differences.push(format!("cycle {} was {} {:02X} at ${:04X}, expected {} {:02X} at ${:04X}",
+            i + 1, kind, value, address, e_kind, e_value, e_address));
+    }
+    (differences, actual_cycles)
+}